@@ -5,8 +5,13 @@ use bevy::prelude::*;
 use rand::seq::SliceRandom;
 
 use crate::combat::{
-    phases::TransitionPhaseEvent, skills::TargetOption, Action, ActionCount, CombatResources,
-    CombatState, CurrentAlterations, InCombat, Recruted, Skills,
+    alterations::AlterationAction,
+    phases::TransitionPhaseEvent,
+    skills::{random_targets, Skill, TargetOption},
+    stats::Hp,
+    tactical_position::depth,
+    Action, ActionCount, CombatResources, CombatState, CurrentAlterations, InCombat, Recruted,
+    Skills, TacticalPosition,
 };
 
 pub fn ai_decision_making(
@@ -17,13 +22,20 @@ pub fn ai_decision_making(
             Entity,
             &Skills,
             &CurrentAlterations,
+            &TacticalPosition,
             &mut ActionCount,
             &Name,
         ),
         (Without<Recruted>, With<InCombat>),
     >,
-    enemies_immutable_query: Query<Entity, (Without<Recruted>, With<InCombat>)>,
-    allies_query: Query<Entity, (With<Recruted>, With<InCombat>)>,
+    enemies_positions_query: Query<
+        (Entity, &Hp, &TacticalPosition),
+        (Without<Recruted>, With<InCombat>),
+    >,
+    allies_alterations_query: Query<
+        (Entity, &Hp, &CurrentAlterations, &TacticalPosition),
+        (With<Recruted>, With<InCombat>),
+    >,
 
     mut transition_phase_event: EventWriter<TransitionPhaseEvent>,
 ) {
@@ -39,7 +51,61 @@ pub fn ai_decision_making(
     //     // iterate over all possible combination without repetition
     // }
 
-    for (caster, skills, _alterations, mut action_count, name) in enemies_mut_query.iter_mut() {
+    // ---- Taunt ----
+    let taunters: Vec<Entity> = allies_alterations_query
+        .iter()
+        .filter(|(_, hp, alterations, _)| {
+            hp.current > 0 && alterations.contains_action(AlterationAction::Taunt)
+        })
+        .map(|(ally, _, _, _)| ally)
+        .collect();
+
+    // ---- Range ----
+    let alive_enemies_positions: Vec<TacticalPosition> = enemies_positions_query
+        .iter()
+        .filter(|(_, hp, _)| hp.current > 0)
+        .map(|(_, _, position)| *position)
+        .collect();
+
+    // ---- Alive ----
+    // the knocked out units can't be targeted
+    let alive_enemies: Vec<Entity> = enemies_positions_query
+        .iter()
+        .filter(|(_, hp, _)| hp.current > 0)
+        .map(|(enemy, _, _)| enemy)
+        .collect();
+    let alive_allies: Vec<Entity> = allies_alterations_query
+        .iter()
+        .filter(|(_, hp, _, _)| hp.current > 0)
+        .map(|(ally, _, _, _)| ally)
+        .collect();
+    let alive_allies_positions: Vec<TacticalPosition> = allies_alterations_query
+        .iter()
+        .filter(|(_, hp, _, _)| hp.current > 0)
+        .map(|(_, _, _, position)| *position)
+        .collect();
+
+    for (caster, skills, alterations, caster_position, mut action_count, name) in
+        enemies_mut_query.iter_mut()
+    {
+        let caster_depth = depth(caster_position, &alive_enemies_positions);
+        // The alive targets a single/multi-target skill can reach
+        let reachable_targets = |skill: &Skill, target_number: usize| -> Vec<Entity> {
+            allies_alterations_query
+                .iter()
+                .filter(|(_, hp, _, _)| hp.current > 0)
+                .filter(|(ally, _, _, _)| {
+                    target_number != 1 || taunters.is_empty() || taunters.contains(ally)
+                })
+                .filter(|(_, _, _, position)| {
+                    skill
+                        .range
+                        .reaches(caster_depth, depth(position, &alive_allies_positions))
+                })
+                .map(|(ally, _, _, _)| ally)
+                .collect()
+        };
+
         // info!("{} has {} actions to decide", name, action_count.current);
         while action_count.current > 0 {
             // Decision
@@ -50,79 +116,85 @@ pub fn ai_decision_making(
             }
             // let random_index = rng.gen_range(0..skills.len());
             // let skill = skills[random_index];
-            let skill = skills.choose(&mut rng).unwrap();
-            
+
+            // ---- Control ----
+            let allowed_skills: Vec<&Skill> = skills
+                .iter()
+                .filter(|skill| skill.is_allowed(alterations))
+                .filter(|skill| match skill.target_option {
+                    TargetOption::Enemy(target_number) => {
+                        !reachable_targets(*skill, target_number).is_empty()
+                    }
+                    TargetOption::RandomEnemy(_) => !alive_allies.is_empty(),
+                    _ => true,
+                })
+                .collect();
+            let forced_pass = Skill::pass();
+            let skill = if alterations.contains_action(AlterationAction::ForcePass) {
+                &forced_pass
+            } else {
+                match allowed_skills.choose(&mut rng) {
+                    Some(skill) => *skill,
+                    None => {
+                        info!("{} can't use any skill", name);
+                        action_count.current = 0;
+                        break;
+                    }
+                }
+            };
+
             // info!("{} has chosen {:?}", name, skill);
 
             let targets: Vec<Entity> = match skill.target_option {
                 TargetOption::OneSelf => vec![caster],
                 TargetOption::Ally(target_number) => {
-                    let mut targets = Vec::new();
-                    for _ in 0..target_number {
-                        let mut allies = Vec::new();
-                        for ally in enemies_immutable_query.iter() {
-                            allies.push(ally)
-                        }
-                        let target = allies.choose(&mut rng).unwrap();
-
-                        // let potential_targets =
-                        //     enemies_immutable_query.iter().collect::<Vec<Entity>>();
-                        // let target = potential_targets.choose(&mut rng).unwrap();
-
-                        // let target = enemies_immutable_query
-                        //     .iter()
-                        //     .collect::<Vec<Entity>>()
-                        //     .choose(&mut rng)
-                        //     .unwrap();
-
-                        targets.push(*target);
-                    }
-                    targets
+                    random_targets(&alive_enemies, target_number, skill.duplicate_targets)
                 }
                 TargetOption::Enemy(target_number) => {
-                    let mut targets = Vec::new();
-                    for _ in 0..target_number {
-                        let potential_targets = allies_query.iter().collect::<Vec<Entity>>();
-                        let target = potential_targets.choose(&mut rng).unwrap();
-                        targets.push(*target);
-                    }
-                    targets
+                    // the Taunt and the skill's range are already checked
+                    let potential_targets = reachable_targets(skill, target_number);
+                    random_targets(&potential_targets, target_number, skill.duplicate_targets)
                 }
                 TargetOption::AllyButSelf(target_number) => {
-                    let mut targets = Vec::new();
-                    for _ in 0..target_number {
-                        let mut allies = Vec::new();
-                        for ally in enemies_immutable_query.iter() {
-                            allies.push(ally)
-                        }
-                        let target = allies.choose(&mut rng).unwrap();
-
-                        if *target != caster {
-                            targets.push(*target);
-                        }
-                        // else unlucky (could lead to `Some(vec![])` but it's ok.)
-                    }
+                    let allies = alive_enemies
+                        .iter()
+                        .filter(|ally| **ally != caster)
+                        .copied()
+                        .collect::<Vec<Entity>>();
+                    // could lead to `Some(vec![])` if alone, but it's ok.
+                    random_targets(&allies, target_number, skill.duplicate_targets)
+                }
+                TargetOption::AllAlly => alive_enemies.clone(),
+                TargetOption::AllEnemy => alive_allies.clone(),
+                TargetOption::All => {
+                    let mut targets: Vec<Entity> = alive_enemies.clone();
+                    targets.extend_from_slice(&alive_allies);
+
                     targets
                 }
-                TargetOption::AllAlly => {
-                    let mut allies = Vec::new();
-                    for ally in enemies_immutable_query.iter() {
-                        allies.push(ally)
-                    }
-                    allies
+                TargetOption::Any(target_number) => {
+                    // its own team or the enemies in range
+                    let mut potential_targets = alive_enemies.clone();
+                    potential_targets.extend(reachable_targets(skill, target_number));
+                    random_targets(&potential_targets, target_number, skill.duplicate_targets)
                 }
-                TargetOption::AllEnemy => allies_query.iter().collect::<Vec<Entity>>(),
-                TargetOption::All => {
-                    // let mut targets: Vec<Entity> = enemies_immutable_query.iter().collect::<Vec<Entity>>();
-                    let mut targets: Vec<Entity> = Vec::new();
-                    for ally in enemies_immutable_query.iter() {
-                        targets.push(ally)
-                    }
-                    let allies = allies_query.iter().collect::<Vec<Entity>>();
-                    targets.extend_from_slice(&allies);
+                TargetOption::AllButSelf => {
+                    let mut targets: Vec<Entity> = alive_enemies
+                        .iter()
+                        .filter(|ally| **ally != caster)
+                        .copied()
+                        .collect();
+                    targets.extend(&alive_allies);
 
                     targets
                 }
+                // the player's units are the AI's enemies
+                TargetOption::RandomEnemy(target_number) => {
+                    random_targets(&alive_allies, target_number, skill.duplicate_targets)
+                }
+                TargetOption::RandomAlly(target_number) => {
+                    random_targets(&alive_enemies, target_number, skill.duplicate_targets)
+                }
             };
 
             // info!("Targeted by {}: {:?}", name, targets);
@@ -4,6 +4,9 @@ use bevy::prelude::*;
 
 use crate::{
     combat::{
+        alterations::{
+            AlterationAction, AlterationSchool, Aura, Auras, Immunities, Immunity, ImmunityFilter,
+        },
         skills::Skill,
         stuff::{Equipements, Job, WeaponBundle},
         ActionCount, AllAlterationStatuses, CombatBundle, CombatState, InCombat, Karma, Player,
@@ -14,7 +17,7 @@ use crate::{
         combat::{team::*, FIRST_ALLY_ID, FIRST_ENEMY_ID},
     },
     spritesheet::FabienSheet,
-    ui::player_interaction::{Clickable, Hoverable, SpriteSize},
+    ui::player_interaction::{Clickable, Draggable, Hoverable, SpriteSize},
 };
 
 pub mod ai;
@@ -80,12 +83,19 @@ fn spawn_characters(mut commands: Commands, fabien: Res<FabienSheet>) {
             CombatBundle {
                 team: Team(Some(TEAM_MC)),
                 karma: Karma(200),
-                skills: Skills(vec![Skill::bam(), Skill::pass()]),
+                skills: Skills(vec![
+                    Skill::bam(),
+                    Skill::swap(),
+                    Skill::advance(),
+                    Skill::retreat(),
+                    Skill::pass(),
+                ]),
                 equipements: Equipements {
                     weapon: Some(bass),
                     armor: None,
                 },
                 job: Job::Musician,
+                auras: Auras(vec![Aura::bulwark(), Aura::tempo()]),
                 action_count: ActionCount::new(20),
                 tactical_position: TacticalPosition::MiddleLine(TacticalPlace::Middle),
                 ..Default::default()
@@ -93,6 +103,7 @@ fn spawn_characters(mut commands: Commands, fabien: Res<FabienSheet>) {
             // -- UI Related Components --
             Hoverable,
             Clickable,
+            Draggable,
         ))
         .with_children(|parent| {
             // Contains all current alterations with their icons
@@ -136,9 +147,11 @@ fn spawn_characters(mut commands: Commands, fabien: Res<FabienSheet>) {
                     Skill::bam(),
                     Skill::gifle(),
                     Skill::diffamation(),
+                    Skill::swap(),
                     Skill::pass(),
                 ]),
                 job: Job::Musician,
+                auras: Auras(vec![Aura::bulwark(), Aura::tempo()]),
                 action_count: ActionCount::new(1),
                 tactical_position: TacticalPosition::FrontLine(TacticalPlace::Left),
                 ..Default::default()
@@ -146,6 +159,7 @@ fn spawn_characters(mut commands: Commands, fabien: Res<FabienSheet>) {
             // -- UI Related Components --
             Hoverable,
             Clickable,
+            Draggable,
         ))
         .with_children(|parent| {
             // Contains all current alterations with their icons
@@ -185,13 +199,23 @@ fn spawn_characters(mut commands: Commands, fabien: Res<FabienSheet>) {
             CombatBundle {
                 team: Team(Some(TEAM_MC)),
                 karma: Karma(100),
-                skills: Skills(vec![Skill::bam(), Skill::implosion(), Skill::pass()]),
+                skills: Skills(vec![
+                    Skill::bam(),
+                    Skill::implosion(),
+                    Skill::ricochet(),
+                    Skill::ward(),
+                    Skill::purify(),
+                    Skill::disenchant(),
+                    Skill::pass(),
+                ]),
+                auras: Auras(vec![Aura::bulwark()]),
                 tactical_position: TacticalPosition::FrontLine(TacticalPlace::Middle),
                 ..Default::default()
             },
             // -- UI Related Components --
             Hoverable,
             Clickable,
+            Draggable,
         ))
         .with_children(|parent| {
             // Contains all current alterations with their icons
@@ -230,7 +254,15 @@ fn spawn_characters(mut commands: Commands, fabien: Res<FabienSheet>) {
             CombatBundle {
                 team: Team(Some(TEAM_OLF)),
                 karma: Karma(-100),
-                skills: Skills(vec![Skill::implosion(), Skill::bam(), Skill::pass()]),
+                skills: Skills(vec![
+                    Skill::implosion(),
+                    Skill::bam(),
+                    Skill::crush(),
+                    Skill::headbutt(),
+                    Skill::bramble(),
+                    Skill::entangle(),
+                    Skill::pass(),
+                ]),
                 equipements: Equipements {
                     weapon: None,
                     armor: None,
@@ -278,12 +310,23 @@ fn spawn_characters(mut commands: Commands, fabien: Res<FabienSheet>) {
                 CombatBundle {
                     team: Team(Some(TEAM_OLF)),
                     karma: Karma(-100),
-                    skills: Skills(vec![Skill::bam(), Skill::pass()]),
+                    skills: Skills(vec![Skill::bam(), Skill::shove(), Skill::pass()]),
                     equipements: Equipements {
                         weapon: Some(smallmouth_bass),
                         armor: None,
                     },
                     job: Job::Fabicurion,
+                    // Fabien's Army is trained to stand firm
+                    immunities: Immunities(vec![
+                        Immunity {
+                            filter: ImmunityFilter::Action(AlterationAction::Stun),
+                            resistance: 100,
+                        },
+                        Immunity {
+                            filter: ImmunityFilter::School(AlterationSchool::Dark),
+                            resistance: 50,
+                        },
+                    ]),
                     tactical_position: if i == 0 {
                         TacticalPosition::MiddleLine(TacticalPlace::Right)
                     } else {
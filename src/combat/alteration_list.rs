@@ -1,8 +1,12 @@
 //! List all the technic possible
 
-use crate::combat::skills::TargetOption;
-
-use super::alterations::{Alteration, AlterationAction};
+use super::{
+    alterations::{
+        Alteration, AlterationAction, AlterationNature, AlterationSchool, Aura, AuraArea,
+        StackingPolicy, Trigger, TriggerEffect,
+    },
+    TacticalLine,
+};
 
 impl Alteration {
     // TODO: ShouldHave - a Counter/Alteration and turn updated in the description
@@ -16,8 +20,10 @@ impl Alteration {
         Alteration {
             action: AlterationAction::StatsPercentage,
             duration: 2,
-            target_option: TargetOption::Enemy(1),
+            stacking: StackingPolicy::Stack(3),
             damage_suffered: 25,
+            nature: AlterationNature::Debuff,
+            school: Some(AlterationSchool::Dark),
             description: String::from("+25% damage suffered for 2turns"),
             name: String::from("Honte"),
             path_icon: String::from("textures/icons/skills-alterations/Dark/Dark_9.png"),
@@ -25,19 +31,56 @@ impl Alteration {
         }
     }
 
-    /// Is a Buff without the debuff Honte
-    /// IDEA: if have Honte -> debuff: cry and turn skip
-    /// order ? or whatever
-    /// IDEA: can only use Skill which attack, or Pass
-    pub fn anger() -> Self {
+    // --- Control ---
+
+    pub fn silence() -> Self {
         Alteration {
-            action: AlterationAction::StatsPercentage,
-            duration: 3,
-            target_option: TargetOption::Enemy(1),
-            damage_inflicted: 25,
-            description: String::from("+25% damage inflicted for 2turns"),
-            name: String::from("Anger"),
-            path_icon: String::from("textures/icons/skills-alterations/Dark/Dark_11.png"),
+            action: AlterationAction::Mute,
+            duration: 2,
+            nature: AlterationNature::Debuff,
+            school: Some(AlterationSchool::Dark),
+            description: String::from("Can't use skills costing mana for 2turns"),
+            name: String::from("Silence"),
+            path_icon: String::from("textures/icons/skills-alterations/Dark/Dark_4.png"),
+            ..Default::default()
+        }
+    }
+
+    pub fn terror() -> Self {
+        Alteration {
+            action: AlterationAction::ForcePass,
+            duration: 1,
+            nature: AlterationNature::Debuff,
+            school: Some(AlterationSchool::Dark),
+            description: String::from("Can only pass for 1turn"),
+            name: String::from("Terror"),
+            path_icon: String::from("textures/icons/skills-alterations/Dark/Dark_6.png"),
+            ..Default::default()
+        }
+    }
+
+    pub fn stun() -> Self {
+        Alteration {
+            action: AlterationAction::Stun,
+            duration: 1,
+            nature: AlterationNature::Debuff,
+            school: Some(AlterationSchool::Dark),
+            description: String::from("No action for 1turn"),
+            name: String::from("Stun"),
+            path_icon: String::from("textures/icons/skills-alterations/Dark/Dark_2.png"),
+            ..Default::default()
+        }
+    }
+
+    pub fn rooted() -> Self {
+        Alteration {
+            action: AlterationAction::Root,
+            duration: 2,
+            nature: AlterationNature::Debuff,
+            school: Some(AlterationSchool::Nature),
+            description: String::from("Can't move for 2turns"),
+            name: String::from("Rooted"),
+            path_icon: String::from("textures/icons/skills-alterations/Nature/Nature_5.png"),
             ..Default::default()
         }
     }
@@ -52,8 +95,9 @@ impl Alteration {
         Alteration {
             action: AlterationAction::StatsPercentage,
             duration: 2,
-            target_option: TargetOption::Ally(1),
             heal_received: 25,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Holy),
             description: String::from("+25% received heal for 2turns"),
             name: String::from("Harmonize"),
             path_icon: String::from("textures/icons/skills-alterations/Holy/Holy_5.png"),
@@ -61,14 +105,36 @@ impl Alteration {
         }
     }
 
+    /// Is a Buff without the debuff Honte
+    /// IDEA: if have Honte -> debuff: cry and turn skip
+    /// order ? or whatever
+    /// IDEA: can only use Skill which attack, or Pass
+    pub fn anger() -> Self {
+        Alteration {
+            action: AlterationAction::StatsPercentage,
+            duration: 3,
+            damage_inflicted: 25,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Dark),
+            description: String::from("+25% damage inflicted for 2turns"),
+            name: String::from("Anger"),
+            path_icon: String::from("textures/icons/skills-alterations/Dark/Dark_11.png"),
+            ..Default::default()
+        }
+    }
+
     // --- Heal ---
 
+    /// Scaled by the healer's `AttackSpe`, and ends if they are knocked out
     pub fn regenerate() -> Self {
         Alteration {
             action: AlterationAction::Dots,
             duration: 3,
-            target_option: TargetOption::Ally(1),
+            stacking: StackingPolicy::Stack(2),
+            channeled: true,
             hp: 10,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Nature),
             description: String::from("10hp per turn for 3turns"),
             name: String::from("Regenerate"),
             path_icon: String::from("textures/icons/skills-alterations/Nature/Nature_9.png"),
@@ -82,8 +148,9 @@ impl Alteration {
         Alteration {
             action: AlterationAction::StatsFlat,
             duration: 3,
-            target_option: TargetOption::Ally(1),
             initiative: 30,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Nature),
             description: "Grant +30initiative for 3turns".to_string(),
             name: "Swiftness".to_string(),
             path_icon: String::from("textures/icons/skills-alterations/Nature/Nature_2.png"),
@@ -95,8 +162,10 @@ impl Alteration {
         Alteration {
             action: AlterationAction::StatsFlat,
             duration: 3,
-            target_option: TargetOption::Ally(1),
+            stacking: StackingPolicy::ReplaceIfStronger,
             defense: 15,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Holy),
             description: "Grant +15defense for 3turns".to_string(),
             name: "Hardness".to_string(),
             path_icon: String::from("textures/icons/skills-alterations/Holy/Holy_10.png"),
@@ -104,6 +173,128 @@ impl Alteration {
         }
     }
 
+    // --- Shield ---
+
+    /// The magic shield given by `Skill::ward()` fades away
+    pub fn fading_ward() -> Self {
+        Alteration {
+            action: AlterationAction::Dots,
+            duration: 3,
+            magic_shield: -10,
+            nature: AlterationNature::Neutral,
+            school: Some(AlterationSchool::Holy),
+            description: "Lose 10magic shield per turn for 3turns".to_string(),
+            name: "Fading Ward".to_string(),
+            path_icon: String::from("textures/icons/skills-alterations/Holy/Holy_3.png"),
+            ..Default::default()
+        }
+    }
+
+    // --- Control ---
+
+    /// Enemies' single-target skills must target the one provoking
+    pub fn provoke() -> Self {
+        Alteration {
+            action: AlterationAction::Taunt,
+            duration: 1,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Holy),
+            description: "Enemies must target you for 1turn".to_string(),
+            name: "Provoke".to_string(),
+            path_icon: String::from("textures/icons/skills-alterations/Holy/Holy_8.png"),
+            ..Default::default()
+        }
+    }
+
+    // --- Triggers ---
+
+    pub fn thorns() -> Self {
+        Alteration {
+            action: AlterationAction::StatsFlat,
+            duration: 3,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Nature),
+            triggers: vec![(Trigger::OnDamaged, TriggerEffect::Thorns(30))],
+            description: "Reflect 30% of the damage suffered for 3turns".to_string(),
+            name: "Thorns".to_string(),
+            path_icon: String::from("textures/icons/skills-alterations/Nature/Nature_4.png"),
+            ..Default::default()
+        }
+    }
+
+    pub fn vampirism() -> Self {
+        Alteration {
+            action: AlterationAction::StatsFlat,
+            duration: 3,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Dark),
+            triggers: vec![(Trigger::OnHit, TriggerEffect::Lifesteal(25))],
+            description: "Heal 25% of the damage dealt for 3turns".to_string(),
+            name: "Vampirism".to_string(),
+            path_icon: String::from("textures/icons/skills-alterations/Dark/Dark_7.png"),
+            ..Default::default()
+        }
+    }
+
+    /// Explode when knocked out
+    pub fn volatile() -> Self {
+        Alteration {
+            action: AlterationAction::StatsFlat,
+            duration: 5,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Nature),
+            triggers: vec![(Trigger::OnDeath, TriggerEffect::Explode(30))],
+            description: "Deal 30dmg to all enemies when knocked out".to_string(),
+            name: "Volatile".to_string(),
+            path_icon: String::from("textures/icons/skills-alterations/Nature/Nature_1.png"),
+            ..Default::default()
+        }
+    }
+
+    pub fn riposte() -> Self {
+        Alteration {
+            action: AlterationAction::StatsFlat,
+            duration: 1,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Holy),
+            triggers: vec![(Trigger::OnTargeted, TriggerEffect::Counterattack(15))],
+            description: "Counterattack for 15dmg when targeted for 1turn".to_string(),
+            name: "Riposte".to_string(),
+            path_icon: String::from("textures/icons/skills-alterations/Holy/Holy_6.png"),
+            ..Default::default()
+        }
+    }
+
+    // --- Aura ---
+
+    /// See `Aura::bulwark()`
+    pub fn bulwark() -> Self {
+        Alteration {
+            action: AlterationAction::StatsFlat,
+            defense: 10,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Holy),
+            description: "+10defense while covered by the front line".to_string(),
+            name: "Bulwark".to_string(),
+            path_icon: String::from("textures/icons/skills-alterations/Holy/Holy_1.png"),
+            ..Default::default()
+        }
+    }
+
+    /// See `Aura::tempo()`
+    pub fn tempo() -> Self {
+        Alteration {
+            action: AlterationAction::StatsFlat,
+            initiative: 10,
+            nature: AlterationNature::Buff,
+            school: Some(AlterationSchool::Nature),
+            description: "+10initiative while next to a musician".to_string(),
+            name: "Tempo".to_string(),
+            path_icon: String::from("textures/icons/skills-alterations/Nature/Nature_3.png"),
+            ..Default::default()
+        }
+    }
+
     /* -------------------------------------------------------------------------- */
     /*                            ------ Neutral ------                           */
     /* -------------------------------------------------------------------------- */
@@ -113,7 +304,9 @@ impl Alteration {
             // TODO: AlterationAction::Nothing
             action: AlterationAction::StatsFlat,
             duration: 2,
-            target_option: TargetOption::Ally(1),
+            stacking: StackingPolicy::Ignore,
+            nature: AlterationNature::Neutral,
+            school: Some(AlterationSchool::Nature),
             description: "You stink to high heaven".to_string(),
             name: "Stale Odour".to_string(),
             path_icon: String::from("textures/icons/skills-alterations/Nature/Nature_6.png"),
@@ -121,3 +314,27 @@ impl Alteration {
         }
     }
 }
+
+/* -------------------------------------------------------------------------- */
+/*                              ------ Aura ------                            */
+/* -------------------------------------------------------------------------- */
+
+impl Aura {
+    /// A fighter in the front line covers the ally directly behind
+    pub fn bulwark() -> Self {
+        Aura {
+            area: AuraArea::Behind,
+            line: Some(TacticalLine::FrontLine),
+            alteration: Alteration::bulwark(),
+        }
+    }
+
+    /// A musician gives rhythm to the allies next to them
+    pub fn tempo() -> Self {
+        Aura {
+            area: AuraArea::Adjacent,
+            line: None,
+            alteration: Alteration::tempo(),
+        }
+    }
+}
@@ -1,11 +1,15 @@
 //! Implement all Combat Buffs and Debuffs
 
 // use std::default;
+use std::fmt;
 
 use bevy::prelude::*;
 // // use bevy_inspector_egui::prelude::*;
 
-use super::skills::*;
+use super::{
+    stats::{percentage_of, Hp, MagicShield, Mana, Shield},
+    TacticalLine, TacticalPosition,
+};
 
 // #[derive(Debug, Clone, Default)]
 // pub enum AlterationAction {
@@ -19,18 +23,113 @@ use super::skills::*;
 
 #[derive(Default, Debug, Clone, Reflect, PartialEq)]
 pub enum AlterationAction {
+    /// hp/mana/shield dealt or healed each turn
     #[default]
     Dots,
+    /// +x to the stats while active
     StatsFlat,
+    /// +x% to the stats while active
     StatsPercentage,
-    /// ??
+    /// x% of the max hp/mana (and of the current shields) dealt or healed each turn
     PercentageAsDots,
-    // TODO: Mute, ForcePass, as StatsFlat but for `duration` turn
+    // ----- Control -----
+    /// Can't use skills which cost mana
+    Mute,
+    /// Can only Pass
+    ForcePass,
+    /// The `ActionCount` is reset to 0 each new turn
+    Stun,
+    /// Enemies' single-target skills (`TargetOption::Enemy(1)`) must target the taunter
+    Taunt,
+    /// Can't change of `TacticalPosition` (no `SkillType::Move` and can't be swapped)
+    Root,
+}
+
+/// When a triggered effect of an alteration occurs
+///
+/// # Note
+///
+/// The effects at the start of each turn are handled by `AlterationAction::Dots`
+#[derive(Debug, Clone, Copy, Reflect, PartialEq, Eq)]
+pub enum Trigger {
+    /// The bearer deals damage with a skill
+    OnHit,
+    /// The bearer suffers damage from a skill
+    OnDamaged,
+    /// The bearer is targeted by a harmful skill
+    OnTargeted,
+    /// The bearer is knocked out
+    OnDeath,
+}
+
+/// The effect of a `Trigger`, performed by the bearer as a skill
+/// (see `Skill::triggered()`)
+#[derive(Debug, Clone, Reflect, PartialEq)]
+pub enum TriggerEffect {
+    /// Reflect x% of the damage suffered to the attacker
+    Thorns(i32),
+    /// Heal the bearer by x% of the damage dealt
+    Lifesteal(i32),
+    /// Deal x dmg to all the bearer's enemies
+    Explode(i32),
+    /// Deal x dmg to the attacker
+    Counterattack(i32),
+}
+
+/// Is the alteration beneficial or harmful to its bearer
+#[derive(Default, Debug, Clone, Copy, Reflect, PartialEq, Eq)]
+pub enum AlterationNature {
+    Buff,
+    Debuff,
+    #[default]
+    Neutral,
+}
+
+/// The magic school of the alteration
+/// (as sorted in `textures/icons/skills-alterations/`)
+#[derive(Debug, Clone, Copy, Reflect, PartialEq, Eq)]
+pub enum AlterationSchool {
+    Dark,
+    Holy,
+    Nature,
+}
+
+/// How an alteration is applied to a target
+/// which already has (at least) one alteration of the same `name`.
+#[derive(Default, Debug, Clone, Reflect, PartialEq)]
+pub enum StackingPolicy {
+    /// The new one refreshes the duration of the ongoing one
+    #[default]
+    Refresh,
+    /// Up to `n` alterations at the same time,
+    /// beyond that the new one replaces the oldest
+    ///
+    /// `Stack(0)` is never applied
+    Stack(usize),
+    /// The new one replaces the ongoing one only if it's stronger
+    ///
+    /// See `Alteration::is_stronger_than()`
+    ReplaceIfStronger,
+    /// The new one is ignored
+    Ignore,
 }
 
 /// Alteration will last for exactly `duration` turn,
 /// can be cured/removed by some clean skills.
 ///
+/// # Lifecycle
+///
+/// - Inserted by a skill or an aura w.r.t. its `StackingPolicy`
+/// (see `CurrentAlterations::insert()`)
+/// - OnTick: executed at each `CombatState::AlterationsExecution`
+/// while `duration` > 0 (see `phases::execute_alteration()`)
+/// - OnExpire: its `duration` is over, it leaves at the next tick without any effect
+/// - OnRemove: cleansed or dispelled by a skill (see `Skill::dispel`),
+/// or evicted/replaced by a new one of the same name
+///
+/// The stats modifiers are only computed while the alteration is active
+/// (see `stats::apply_modifiers()`), so leaving reverts them.
+///
 /// # Note
 ///
 /// IDEA: Alteration can 'control' stats (threshold, limit?)
@@ -48,19 +147,27 @@ pub struct Alteration {
     pub turn_count: i32,
     /// Number of turn remaining
     pub duration: i32,
-    /// Which side the skill is allow to target
-    /// and how many (0 for self-target)
-    ///
-    /// # Example
-    ///
-    /// - target all ally party: (Ally, 6)
-    /// - self-target: (Ally, 0)
+    /// What happens when the target already has this alteration
+    pub stacking: StackingPolicy,
+    /// Buff, Debuff or Neutral, used by cleanse/dispel skills
+    pub nature: AlterationNature,
+    /// `None` if the alteration doesn't belong to any school
+    pub school: Option<AlterationSchool>,
+    /// Effects performed by the bearer when the trigger occurs
+    pub triggers: Vec<(Trigger, TriggerEffect)>,
+    /// The stats modifiers grow each turn
     ///
-    /// # Note
+    /// See `Alteration::escalate()`
+    pub escalating: bool,
+    /// Emanates from an ally's `Aura`
     ///
-    /// REFACTOR: Target Option should only be managed by the skill calling the alteration
-    /// TODO: Remove target_option
-    pub target_option: TargetOption,
+    /// Doesn't expire and can't be dispelled:
+    /// it lasts as long as the source is alive and in place.
+    pub aura: bool,
+    /// Who applied the alteration, `None` if it wasn't applied by a skill
+    pub source: Option<AlterationSource>,
+    /// Ends when its source is knocked out
+    pub channeled: bool,
 
     /// hp dealt or healed each time the target plays
     ///
@@ -74,6 +181,10 @@ pub struct Alteration {
     ///
     /// shield: reduce/addition to the target
     pub shield: i32,
+    /// magic shield point reduced or added each time the target plays
+    ///
+    /// magic shield: reduce/addition to the target
+    pub magic_shield: i32,
     // initiative: lose/gain
     pub initiative: i32,
     /// att: lose/gain
@@ -114,10 +225,18 @@ impl Default for Alteration {
             action: AlterationAction::Dots,
             turn_count: 0,
             duration: 1,
-            target_option: TargetOption::Enemy(1),
+            stacking: StackingPolicy::Refresh,
+            nature: AlterationNature::Neutral,
+            school: None,
+            triggers: vec![],
+            escalating: false,
+            aura: false,
+            source: None,
+            channeled: false,
             hp: 0,
             mana: 0,
             shield: 0,
+            magic_shield: 0,
             initiative: 0,
             attack: 0,
             attack_spe: 0,
@@ -134,6 +253,215 @@ impl Default for Alteration {
     }
 }
 
+impl Alteration {
+    /// At each turn, we increment/decrement the alteration's stats
+    ///
+    /// ----- EX: +10% attack/turn -----
+    ///
+    /// - t0: alt.attck = 10;  When the alteration is inserted
+    /// - t1: 10 + 10/1;       When the first altPhase occurs
+    /// - ...
+    /// - t4: 10 + 10/1 + 20/2 + 30/3 + 40/4;
+    /// - t5: 10 + 10/1 + 20/2 + 30/3 + 40/4 + 50/5;
+    pub fn escalate(&mut self) {
+        if self.turn_count != 0 {
+            self.attack += self.attack / self.turn_count;
+            self.attack_spe += self.attack_spe / self.turn_count;
+            self.defense += self.defense / self.turn_count;
+            self.defense_spe += self.defense_spe / self.turn_count;
+        }
+    }
+
+    /// Tick of a `AlterationAction::PercentageAsDots`
+    ///
+    /// - hp/mana: x% of the max (can't overheal, mana clamped to `0..=max`)
+    /// - shields: x% of the current one (they don't have max)
+    pub fn tick_percentage(
+        &self,
+        hp: &mut Hp,
+        mp: &mut Mana,
+        shield: &mut Shield,
+        magic_shield: &mut MagicShield,
+    ) {
+        hp.current = (hp.current + percentage_of(hp.max, self.hp)).min(hp.max);
+        mp.current = (mp.current + percentage_of(mp.max, self.mana)).clamp(0, mp.max);
+        shield.0 += percentage_of(shield.0, self.shield);
+        magic_shield.0 += percentage_of(magic_shield.0, self.magic_shield);
+    }
+
+    /// hp/mana dealt or healed each turn,
+    /// scaled by the source's `AttackSpe` when the alteration was applied
+    ///
+    /// value * (100 + source_attack_spe)%
+    pub fn scaled(&self, value: i32) -> i32 {
+        match &self.source {
+            None => value,
+            Some(source) => value * (100 + source.attack_spe) / 100,
+        }
+    }
+
+    /// "Regenerate from Hugo", or just "Regenerate" without source
+    pub fn attribution(&self) -> String {
+        match &self.source {
+            None => self.name.clone(),
+            Some(source) => format!("{} from {}", self.name, source.name),
+        }
+    }
+
+    /// Sum of all the effects' magnitude
+    pub fn potency(&self) -> i32 {
+        [
+            self.hp,
+            self.mana,
+            self.shield,
+            self.magic_shield,
+            self.initiative,
+            self.attack,
+            self.attack_spe,
+            self.defense,
+            self.defense_spe,
+            self.damage_inflicted,
+            self.damage_suffered,
+            self.heal_inflicted,
+            self.heal_received,
+        ]
+        .iter()
+        .map(|effect| effect.abs())
+        .sum()
+    }
+
+    /// Compare the potency then, in case of equality, the remaining duration.
+    pub fn is_stronger_than(&self, other: &Alteration) -> bool {
+        (self.potency(), self.duration) > (other.potency(), other.duration)
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                 Immunities                                 */
+/* -------------------------------------------------------------------------- */
+
+/// Which alterations an `Immunity` applies to
+#[derive(Debug, Clone, Reflect, PartialEq)]
+pub enum ImmunityFilter {
+    /// An alteration's `name`
+    Name(String),
+    Action(AlterationAction),
+    Nature(AlterationNature),
+    School(AlterationSchool),
+}
+
+impl ImmunityFilter {
+    pub fn matches(&self, alteration: &Alteration) -> bool {
+        match self {
+            ImmunityFilter::Name(name) => *name == alteration.name,
+            ImmunityFilter::Action(action) => *action == alteration.action,
+            ImmunityFilter::Nature(nature) => *nature == alteration.nature,
+            ImmunityFilter::School(school) => Some(*school) == alteration.school,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Reflect, PartialEq)]
+pub struct Immunity {
+    pub filter: ImmunityFilter,
+    /// - 100 = the alteration is blocked
+    /// - x = the alteration's duration is reduced by x%, rounded to the nearest turn
+    ///   (see `Immunities::resist()`)
+    pub resistance: i32,
+}
+
+impl fmt::Display for Immunity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let filter = match &self.filter {
+            ImmunityFilter::Name(name) => name.clone(),
+            ImmunityFilter::Action(action) => format!("{:?}", action),
+            ImmunityFilter::Nature(nature) => format!("{:?}", nature),
+            ImmunityFilter::School(school) => format!("{:?}", school),
+        };
+        if self.resistance >= 100 {
+            write!(f, "{}", filter)
+        } else {
+            write!(f, "{} (-{}%)", filter, self.resistance)
+        }
+    }
+}
+
+/// Alterations blocked, or with a reduced duration, on this fighter
+#[derive(Component, Default, Debug, Clone, Deref, DerefMut, Reflect)]
+pub struct Immunities(pub Vec<Immunity>);
+
+impl Immunities {
+    /// Returns the alteration with its duration reduced by the strongest resistance,
+    /// or `None` if the fighter is immune to it.
+    ///
+    /// The reduction is rounded to the nearest turn (half up), so the short alterations
+    /// can be resisted too: a 1-turn alteration is cut to 0 from 50%,
+    /// a 2-turn one loses a turn from 25% and both from 75%.
+    /// A duration cut to 0 means the alteration is fully resisted.
+    pub fn resist(&self, mut alteration: Alteration) -> Option<Alteration> {
+        let resistance = self
+            .iter()
+            .filter(|immunity| immunity.filter.matches(&alteration))
+            .map(|immunity| immunity.resistance)
+            .max()
+            .unwrap_or(0);
+
+        if resistance >= 100 {
+            return None;
+        }
+        alteration.duration -= (alteration.duration * resistance + 50) / 100;
+        Some(alteration)
+    }
+}
+
+/// Who applied an alteration, and a snapshot of their stats
+/// (with their modifiers) at that time
+#[derive(Debug, Clone, Reflect, PartialEq)]
+pub struct AlterationSource {
+    pub entity: Entity,
+    /// Kept to credit the source in the logs
+    pub name: String,
+    /// Scales the dots (see `Alteration::scaled()`)
+    pub attack_spe: i32,
+}
+
+/// Where an `Aura` emanates, relative to its source
+#[derive(Debug, Clone, Reflect, PartialEq)]
+pub enum AuraArea {
+    /// The ally directly behind the source (same place, next line)
+    Behind,
+    /// The allies next to the source on the grid (no diagonal)
+    Adjacent,
+    /// The allies sharing the source's line
+    Line,
+}
+
+impl AuraArea {
+    pub fn covers(&self, source: &TacticalPosition, receiver: &TacticalPosition) -> bool {
+        match self {
+            AuraArea::Behind => source.behind() == Some(*receiver),
+            AuraArea::Adjacent => source.is_adjacent_to(receiver),
+            AuraArea::Line => source != receiver && source.line() == receiver.line(),
+        }
+    }
+}
+
+/// Alteration granted to the source's allies w.r.t. their `TacticalPosition`
+#[derive(Debug, Clone, Reflect, PartialEq)]
+pub struct Aura {
+    pub area: AuraArea,
+    /// Only emitted while the source stands in this line (anywhere if `None`)
+    pub line: Option<TacticalLine>,
+    pub alteration: Alteration,
+}
+
+/// Auras emitted by this fighter while alive
+/// (see `tactical_position::update_auras()`)
+///
+/// None by default, the auras are given per character at spawn.
+#[derive(Component, Default, Debug, Clone, Deref, DerefMut, Reflect)]
+pub struct Auras(pub Vec<Aura>);
+
 // /// Happens when
 // ///   - combat::phases::alteration_phase
 // ///     - There is an alteration to execute
@@ -145,3 +473,146 @@ impl Default for Alteration {
 //     pub target: Entity,
 //     pub alteration: Alteration,
 // }
+
+/// How an alteration left its bearer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum AlterationEnd {
+    /// OnExpire: its duration is over, or its aura isn't received anymore
+    Expired,
+    /// OnRemove: cleansed, dispelled, or evicted/replaced by a new one (see `StackingPolicy`)
+    Removed,
+}
+
+/// Happens when
+///   - combat::phases::execute_alteration
+///     - An alteration expired
+///   - combat::skills::execute_skill
+///     - An alteration was cleansed, dispelled, evicted or replaced
+///   - combat::tactical_position::update_auras
+///     - An aura's source was knocked out or moved away,
+///       or a new aura evicted or replaced an alteration
+///
+/// Read in
+///   - ui::combat_system::update_alterations_status
+///     - Refresh the bearer's icons
+///   - ui::combat_system::alterations_end_logs
+///     - Log the expiration
+#[derive(Event)]
+pub struct AlterationEndEvent {
+    pub bearer: Entity,
+    pub alteration: Alteration,
+    pub end: AlterationEnd,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escalate_follows_the_documented_sequence() {
+        let mut alteration = Alteration {
+            attack: 10,
+            escalating: true,
+            ..Default::default()
+        };
+        // t0: nothing happens before the first tick
+        alteration.escalate();
+        assert_eq!(alteration.attack, 10);
+
+        // as in `phases::execute_alteration()`
+        for expected in [20, 30, 40, 50, 60] {
+            alteration.turn_count += 1;
+            alteration.escalate();
+            assert_eq!(alteration.attack, expected);
+        }
+    }
+
+    #[test]
+    fn resistance_rounds_to_the_nearest_turn() {
+        let resisting = |resistance: i32, duration: i32| {
+            let immunities = Immunities(vec![Immunity {
+                filter: ImmunityFilter::Name(String::from("An Alteration")),
+                resistance,
+            }]);
+            immunities
+                .resist(Alteration {
+                    duration,
+                    ..Default::default()
+                })
+                .map(|alteration| alteration.duration)
+        };
+
+        assert_eq!(resisting(0, 1), Some(1));
+        assert_eq!(resisting(49, 1), Some(1));
+        assert_eq!(resisting(50, 1), Some(0));
+        assert_eq!(resisting(25, 2), Some(1));
+        assert_eq!(resisting(75, 2), Some(0));
+        assert_eq!(resisting(30, 3), Some(2));
+        assert_eq!(resisting(100, 3), None);
+    }
+
+    #[test]
+    fn percentage_tick_does_not_overheal() {
+        let alteration = Alteration {
+            action: AlterationAction::PercentageAsDots,
+            hp: 20,
+            ..Default::default()
+        };
+        let mut hp = Hp {
+            current: 45,
+            max: 50,
+        };
+        let mut mp = Mana::default();
+        let (mut shield, mut magic_shield) = (Shield(0), MagicShield(0));
+
+        alteration.tick_percentage(&mut hp, &mut mp, &mut shield, &mut magic_shield);
+        assert_eq!(hp.current, 50);
+
+        let poison = Alteration {
+            hp: -10,
+            ..alteration
+        };
+        poison.tick_percentage(&mut hp, &mut mp, &mut shield, &mut magic_shield);
+        assert_eq!(hp.current, 45);
+    }
+
+    #[test]
+    fn percentage_tick_clamps_mana() {
+        let mut hp = Hp::default();
+        let mut mp = Mana {
+            current: 10,
+            max: 50,
+        };
+        let (mut shield, mut magic_shield) = (Shield(0), MagicShield(0));
+
+        let burn = Alteration {
+            action: AlterationAction::PercentageAsDots,
+            mana: -50,
+            ..Default::default()
+        };
+        burn.tick_percentage(&mut hp, &mut mp, &mut shield, &mut magic_shield);
+        assert_eq!(mp.current, 0);
+
+        mp.current = 45;
+        let regen = Alteration { mana: 20, ..burn };
+        regen.tick_percentage(&mut hp, &mut mp, &mut shield, &mut magic_shield);
+        assert_eq!(mp.current, 50);
+    }
+
+    #[test]
+    fn percentage_tick_scales_the_current_shields() {
+        let alteration = Alteration {
+            action: AlterationAction::PercentageAsDots,
+            shield: 50,
+            magic_shield: -50,
+            ..Default::default()
+        };
+        let mut hp = Hp::default();
+        let mut mp = Mana::default();
+        let (mut shield, mut magic_shield) = (Shield(20), MagicShield(20));
+
+        alteration.tick_percentage(&mut hp, &mut mp, &mut shield, &mut magic_shield);
+        assert_eq!(shield.0, 30);
+        assert_eq!(magic_shield.0, 10);
+    }
+}
@@ -37,7 +37,7 @@ use bevy::prelude::*;
 use crate::{constants::combat::BASE_ACTION_COUNT, ui};
 
 use self::{
-    alterations::Alteration, skills::{Skill, TargetOption, SkillExecutionQueue}, stats::{StatBundle, Hp},
+    alterations::{Alteration, AlterationAction, Auras, Immunities, StackingPolicy, Trigger, TriggerEffect}, skills::{Dispel, DuplicateTargets, Skill, TargetOption, SkillExecutionQueue}, stats::{StatBundle, Hp},
     stuff::{Equipements, JobsMasteries, Job},
 };
 
@@ -72,7 +72,8 @@ pub enum GameState {
 /// REFACTOR: Turn CombatState into States
 #[derive(Default, SystemSet, PartialEq, Eq, Hash, Clone, Debug, Reflect, Resource)]
 pub enum CombatState {
-    /// REFACTOR: Useless atm
+    /// The player arranges their party on their 3x3 grid,
+    /// before the first turn (formation editor)
     Initialisation,
     AlterationsExecution,
     #[default]
@@ -115,15 +116,20 @@ impl Plugin for CombatPlugin {
         app
             
             .add_state::<GameState>()
-            .insert_resource(CombatState::default())
+            // the formation editor opens the combat
+            .insert_resource(CombatState::Initialisation)
             
             .insert_resource(SkillExecutionQueue::default())
             .init_resource::<CombatResources>()
             .init_resource::<JobsMasteries>()
+            .init_resource::<tactical_position::PositionModifiers>()
+            .init_resource::<tactical_position::Formations>()
             
             .add_event::<phases::TransitionPhaseEvent>()
             .add_event::<skills::ExecuteSkillEvent>()
+            .add_event::<alterations::AlterationEndEvent>()
             .add_event::<tactical_position::UpdateCharacterPositionEvent>()
+            .add_event::<tactical_position::MoveFighterEvent>()
             
             .configure_set(
                 Update,
@@ -189,8 +195,27 @@ impl Plugin for CombatPlugin {
                 (
                     phases::phase_transition,
                     update_number_of_fighters,
+                    tactical_position::update_auras,
+                    phases::break_channeled_alterations,
+                    // every damage goes through: skills, dots and collisions
+                    skills::detect_knock_outs
+                        .after(phases::execute_alteration)
+                        .after(tactical_position::move_fighters)
+                        .before(crate::fx::add_skill_vfx),
                 )
             )
+            .add_systems(
+                Update,
+                (
+                    tactical_position::validate_formations,
+                    // the rejected fighters are no longer `InCombat`
+                    apply_deferred,
+                    tactical_position::update_formations,
+                )
+                    .chain()
+                    .after(skills::execute_skill)
+                    .before(tactical_position::move_fighters)
+            )
             .add_systems(
                 Update, 
                 phases::execute_alteration
@@ -212,6 +237,11 @@ impl Plugin for CombatPlugin {
                 Update,
                 (
                     skills::execute_skill,
+                    tactical_position::move_fighters.after(skills::execute_skill),
+                    // once the new knock outs are known, before the next VFX
+                    skills::retarget_knocked_out_targets
+                        .after(skills::detect_knock_outs)
+                        .before(crate::fx::add_skill_vfx),
                 )
                     .in_set(CombatState::ExecuteSkills)
             )
@@ -229,6 +259,8 @@ pub struct CombatBundle {
     pub team: Team,
     pub job: Job,
     pub alterations: CurrentAlterations,
+    pub immunities: Immunities,
+    pub auras: Auras,
     pub skills: Skills,
     pub equipements: Equipements,
     pub action_count: ActionCount,
@@ -244,6 +276,8 @@ impl Default for CombatBundle {
             team: Team(None),
             job: Job::default(),
             alterations: CurrentAlterations::default(),
+            immunities: Immunities::default(),
+            auras: Auras::default(),
             skills: Skills(Vec::new()),
             equipements: Equipements { weapon: None, armor: None },
             action_count: ActionCount::default(),
@@ -284,7 +318,7 @@ impl Default for ActionCount {
 /// REFACTOR: Just an enum...
 /// Listing all possible teams (cause its fixed)
 /// IDEA: Or An reputation meter for each ? struct annex
-#[derive(Component, Copy, Clone, PartialEq, Eq, Deref, DerefMut)]
+#[derive(Component, Copy, Clone, PartialEq, Eq, Hash, Deref, DerefMut)]
 pub struct Team(pub Option<i32>);
 
 /// Ongoing alterations, Debuff or Buff
@@ -293,6 +327,90 @@ pub struct Team(pub Option<i32>);
 #[derive(Default, Component, Deref, DerefMut)]
 pub struct CurrentAlterations(Vec<Alteration>);
 
+impl CurrentAlterations {
+    /// Is there at least one ongoing alteration of this type
+    pub fn contains_action(&self, action: AlterationAction) -> bool {
+        self.0.iter().any(|alteration| alteration.action == action)
+    }
+
+    /// All the effects of the ongoing alterations reacting to this trigger
+    pub fn triggered_effects(&self, trigger: Trigger) -> Vec<TriggerEffect> {
+        self.0
+            .iter()
+            .flat_map(|alteration| alteration.triggers.iter())
+            .filter(|(alteration_trigger, _)| *alteration_trigger == trigger)
+            .map(|(_, effect)| effect.clone())
+            .collect()
+    }
+
+    /// Number of ongoing alterations named `name`
+    pub fn count(&self, name: &str) -> usize {
+        self.0.iter().filter(|alteration| alteration.name == name).count()
+    }
+
+    /// Apply a new alteration w.r.t. its `StackingPolicy`
+    /// against the ongoing ones of the same name.
+    ///
+    /// Returns the ongoing alterations evicted or replaced by the new one,
+    /// the caller has to end them (`AlterationEnd::Removed`).
+    pub fn insert(&mut self, alteration: Alteration) -> Vec<Alteration> {
+        // no room at all
+        if alteration.stacking == StackingPolicy::Stack(0) {
+            return Vec::new();
+        }
+
+        let Some(first_index) =
+            self.0.iter().position(|ongoing| ongoing.name == alteration.name)
+        else {
+            self.0.push(alteration);
+            return Vec::new();
+        };
+
+        match alteration.stacking {
+            StackingPolicy::Refresh => {
+                for ongoing in self.0.iter_mut().filter(|ongoing| ongoing.name == alteration.name) {
+                    ongoing.duration = ongoing.duration.max(alteration.duration);
+                }
+                Vec::new()
+            }
+            StackingPolicy::Stack(max_stack) => {
+                let mut evicted = Vec::new();
+                if self.count(&alteration.name) >= max_stack {
+                    // the oldest is replaced
+                    evicted.push(self.0.remove(first_index));
+                }
+                self.0.push(alteration);
+                evicted
+            }
+            StackingPolicy::ReplaceIfStronger => {
+                if alteration.is_stronger_than(&self.0[first_index]) {
+                    vec![std::mem::replace(&mut self.0[first_index], alteration)]
+                } else {
+                    Vec::new()
+                }
+            }
+            StackingPolicy::Ignore => Vec::new(),
+        }
+    }
+
+    /// Remove up to `dispel.count` alterations matching the `Dispel`'s filter,
+    /// the most recent first.
+    ///
+    /// Returns the removed alterations.
+    pub fn remove_matching(&mut self, dispel: &Dispel) -> Vec<Alteration> {
+        let mut removed = Vec::new();
+        let mut index = self.0.len();
+        while index > 0 && removed.len() < dispel.count {
+            index -= 1;
+            // the auras are managed by their source
+            if !self.0[index].aura && dispel.matches(&self.0[index]) {
+                removed.push(self.0.remove(index));
+            }
+        }
+        removed
+    }
+}
+
 
 /// Marker: Child of a fighter, has as child all the alteration's icon of the fighter
 /// 
@@ -336,6 +454,15 @@ pub enum TacticalPlace {
     Right,
 }
 
+/// From the closest to the enemies to the farthest
+#[derive(Default, Reflect, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum TacticalLine {
+    #[default]
+    FrontLine,
+    MiddleLine,
+    BackLine,
+}
+
 #[derive(Component, Reflect, PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum TacticalPosition {
     FrontLine(TacticalPlace),
@@ -349,6 +476,72 @@ impl Default for TacticalPosition {
     }
 }
 
+impl TacticalPosition {
+    pub fn new(line: TacticalLine, place: TacticalPlace) -> Self {
+        match line {
+            TacticalLine::FrontLine => TacticalPosition::FrontLine(place),
+            TacticalLine::MiddleLine => TacticalPosition::MiddleLine(place),
+            TacticalLine::BackLine => TacticalPosition::BackLine(place),
+        }
+    }
+
+    /// The 9 slots of the grid, from the front line to the back line
+    pub fn all() -> Vec<TacticalPosition> {
+        let places = [TacticalPlace::Left, TacticalPlace::Middle, TacticalPlace::Right];
+        [TacticalLine::FrontLine, TacticalLine::MiddleLine, TacticalLine::BackLine]
+            .iter()
+            .flat_map(|line| places.iter().map(move |place| TacticalPosition::new(*line, *place)))
+            .collect()
+    }
+
+    pub fn line(&self) -> TacticalLine {
+        match self {
+            TacticalPosition::FrontLine(_) => TacticalLine::FrontLine,
+            TacticalPosition::MiddleLine(_) => TacticalLine::MiddleLine,
+            TacticalPosition::BackLine(_) => TacticalLine::BackLine,
+        }
+    }
+
+    pub fn place(&self) -> TacticalPlace {
+        match self {
+            TacticalPosition::FrontLine(place)
+            | TacticalPosition::MiddleLine(place)
+            | TacticalPosition::BackLine(place) => *place,
+        }
+    }
+
+    /// The slot directly in front (same place, previous line)
+    pub fn in_front(&self) -> Option<TacticalPosition> {
+        match self {
+            TacticalPosition::FrontLine(_) => None,
+            TacticalPosition::MiddleLine(place) => Some(TacticalPosition::FrontLine(*place)),
+            TacticalPosition::BackLine(place) => Some(TacticalPosition::MiddleLine(*place)),
+        }
+    }
+
+    /// The slot directly behind (same place, next line)
+    pub fn behind(&self) -> Option<TacticalPosition> {
+        match self {
+            TacticalPosition::FrontLine(place) => Some(TacticalPosition::MiddleLine(*place)),
+            TacticalPosition::MiddleLine(place) => Some(TacticalPosition::BackLine(*place)),
+            TacticalPosition::BackLine(_) => None,
+        }
+    }
+
+    /// Number of steps between the two slots on the 3x3 grid (no diagonal)
+    pub fn distance(&self, other: &TacticalPosition) -> i32 {
+        let line_gap = (self.line() as i32 - other.line() as i32).abs();
+        let place_gap = (self.place() as i32 - other.place() as i32).abs();
+
+        line_gap + place_gap
+    }
+
+    /// Next to each other on the 3x3 grid (no diagonal)
+    pub fn is_adjacent_to(&self, other: &TacticalPosition) -> bool {
+        self.distance(other) == 1
+    }
+}
+
 /* -------------------------------------------------------------------------- */
 /*                         -- Combat Core Operation --                        */
 /* -------------------------------------------------------------------------- */
@@ -449,6 +642,34 @@ impl Action {
         }
     }
 
+    /// Number of alive units on the side(s) the skill can target
+    /// (from the player's point of view)
+    /// 
+    /// Only used for the targets chosen one by one (see `TargetOption::max_targets()`)
+    pub fn targetable_units(&self, number_of_fighters: &GlobalFighterStats) -> usize {
+        match self.skill.target_option {
+            TargetOption::Enemy(_) | TargetOption::RandomEnemy(_) => number_of_fighters.enemy.alive,
+            TargetOption::Ally(_) | TargetOption::RandomAlly(_) => number_of_fighters.ally.alive,
+            TargetOption::AllyButSelf(_) => number_of_fighters.ally.alive.saturating_sub(1),
+            _ => number_of_fighters.enemy.alive + number_of_fighters.ally.alive,
+        }
+    }
+
+    /// No more target can be chosen:
+    /// the maximum is reached, or every targetable unit is already chosen
+    /// (with `DuplicateTargets::Forbidden`)
+    pub fn is_full(&self, number_of_fighters: &GlobalFighterStats) -> bool {
+        match (&self.targets, self.skill.target_option.max_targets()) {
+            (Some(targets), Some(number)) => {
+                targets.len() >= number
+                    || (self.skill.duplicate_targets == DuplicateTargets::Forbidden
+                        && targets.len() >= self.targetable_units(number_of_fighters))
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
     /// Verify if the action has the good number of target depending its skill
     /// 
     /// # Note
@@ -474,9 +695,24 @@ impl Action {
                     None => false
                 }
             }
-            TargetOption::AllyButSelf(number) | TargetOption::Ally(number) | TargetOption::Enemy(number) => {
+            TargetOption::AllButSelf => {
+                match &self.targets {
+                    Some(targets) => targets.len() + 1 == (number_of_fighters.enemy.alive + number_of_fighters.ally.alive),
+                    None => false
+                }
+            }
+            TargetOption::AllyButSelf(number)
+            | TargetOption::Ally(number)
+            | TargetOption::Enemy(number)
+            | TargetOption::Any(number)
+            | TargetOption::RandomEnemy(number)
+            | TargetOption::RandomAlly(number) => {
+                // partial selection
+                let required = self.skill.min_targets
+                    .unwrap_or(number)
+                    .min(self.targetable_units(&number_of_fighters));
                 match &self.targets {
-                    Some(targets) => targets.len() == number,
+                    Some(targets) => required <= targets.len() && targets.len() <= number,
                     None => false
                 }
             }
@@ -608,3 +844,168 @@ pub fn in_browsing_enemy_sheet_phase(combat_state: Res<CombatState>) -> bool {
 pub fn in_evasive_phase(combat_state: Res<CombatState>) -> bool {
     *combat_state == CombatState::Evasion
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poison(stacking: StackingPolicy, duration: i32, hp: i32) -> Alteration {
+        Alteration {
+            name: "Poison".to_string(),
+            stacking,
+            duration,
+            hp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn refresh_keeps_the_longer_duration() {
+        let mut alterations = CurrentAlterations::default();
+        assert!(alterations
+            .insert(poison(StackingPolicy::Refresh, 3, -5))
+            .is_empty());
+        assert!(alterations
+            .insert(poison(StackingPolicy::Refresh, 2, -5))
+            .is_empty());
+        assert_eq!(alterations.count("Poison"), 1);
+        assert_eq!(alterations[0].duration, 3);
+
+        assert!(alterations
+            .insert(poison(StackingPolicy::Refresh, 4, -5))
+            .is_empty());
+        assert_eq!(alterations.count("Poison"), 1);
+        assert_eq!(alterations[0].duration, 4);
+    }
+
+    #[test]
+    fn stack_caps_at_n_and_evicts_the_oldest() {
+        let mut alterations = CurrentAlterations::default();
+        for hp in [-1, -2] {
+            assert!(alterations
+                .insert(poison(StackingPolicy::Stack(2), 3, hp))
+                .is_empty());
+        }
+        assert_eq!(alterations.count("Poison"), 2);
+
+        let evicted = alterations.insert(poison(StackingPolicy::Stack(2), 3, -3));
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].hp, -1);
+        assert_eq!(alterations.count("Poison"), 2);
+        let stacks: Vec<i32> = alterations.iter().map(|alteration| alteration.hp).collect();
+        assert_eq!(stacks, vec![-2, -3]);
+    }
+
+    #[test]
+    fn stack_zero_is_never_applied() {
+        let mut alterations = CurrentAlterations::default();
+        assert!(alterations
+            .insert(poison(StackingPolicy::Stack(0), 3, -5))
+            .is_empty());
+        assert_eq!(alterations.count("Poison"), 0);
+    }
+
+    #[test]
+    fn replace_if_stronger_only_replaces_a_weaker_one() {
+        let mut alterations = CurrentAlterations::default();
+        alterations.insert(poison(StackingPolicy::ReplaceIfStronger, 3, -5));
+
+        assert!(alterations
+            .insert(poison(StackingPolicy::ReplaceIfStronger, 3, -2))
+            .is_empty());
+        assert_eq!(alterations[0].hp, -5);
+
+        let replaced = alterations.insert(poison(StackingPolicy::ReplaceIfStronger, 1, -8));
+        assert_eq!(replaced.len(), 1);
+        assert_eq!(replaced[0].hp, -5);
+        assert_eq!(alterations.count("Poison"), 1);
+        assert_eq!(alterations[0].hp, -8);
+
+        // same potency: the longer one wins
+        let replaced = alterations.insert(poison(StackingPolicy::ReplaceIfStronger, 2, -8));
+        assert_eq!(replaced[0].duration, 1);
+        assert_eq!(alterations[0].duration, 2);
+    }
+
+    #[test]
+    fn ignore_keeps_the_ongoing_one() {
+        let mut alterations = CurrentAlterations::default();
+        alterations.insert(poison(StackingPolicy::Ignore, 2, -5));
+        assert!(alterations
+            .insert(poison(StackingPolicy::Ignore, 5, -9))
+            .is_empty());
+        assert_eq!(alterations.count("Poison"), 1);
+        assert_eq!(alterations[0].duration, 2);
+        assert_eq!(alterations[0].hp, -5);
+    }
+
+    fn multi_target_action(
+        min_targets: Option<usize>,
+        duplicate_targets: DuplicateTargets,
+        targets: Vec<Entity>,
+    ) -> Action {
+        let skill = Skill {
+            target_option: TargetOption::Enemy(3),
+            min_targets,
+            duplicate_targets,
+            ..Default::default()
+        };
+        Action::new(Entity::from_raw(0), skill, Some(targets))
+    }
+
+    fn enemies(number: u32) -> Vec<Entity> {
+        (1..=number).map(Entity::from_raw).collect()
+    }
+
+    #[test]
+    fn a_partial_selection_is_correct_from_min_targets() {
+        let fighters = GlobalFighterStats::new(1, 3);
+
+        let action = multi_target_action(Some(1), DuplicateTargets::Allowed, Vec::new());
+        assert!(!action.is_correct(fighters.clone()));
+
+        let action = multi_target_action(Some(1), DuplicateTargets::Allowed, enemies(1));
+        assert!(action.is_correct(fighters.clone()));
+        assert!(!action.is_full(&fighters));
+
+        let action = multi_target_action(Some(1), DuplicateTargets::Allowed, enemies(3));
+        assert!(action.is_correct(fighters.clone()));
+        assert!(action.is_full(&fighters));
+    }
+
+    #[test]
+    fn without_min_targets_every_target_is_required() {
+        let fighters = GlobalFighterStats::new(1, 3);
+
+        let action = multi_target_action(None, DuplicateTargets::Allowed, enemies(2));
+        assert!(!action.is_correct(fighters.clone()));
+
+        let action = multi_target_action(None, DuplicateTargets::Allowed, enemies(4));
+        assert!(!action.is_correct(fighters));
+    }
+
+    #[test]
+    fn fewer_targets_are_required_when_fewer_units_remain() {
+        let mut fighters = GlobalFighterStats::new(1, 3);
+        fighters.enemy.alive = 2;
+
+        let action = multi_target_action(None, DuplicateTargets::Forbidden, enemies(2));
+        assert!(action.is_correct(fighters.clone()));
+
+        let action = multi_target_action(None, DuplicateTargets::Forbidden, enemies(1));
+        assert!(!action.is_correct(fighters));
+    }
+
+    #[test]
+    fn forbidden_duplicates_are_full_once_every_unit_is_chosen() {
+        let mut fighters = GlobalFighterStats::new(1, 3);
+        fighters.enemy.alive = 2;
+
+        let action = multi_target_action(None, DuplicateTargets::Forbidden, enemies(2));
+        assert!(action.is_full(&fighters));
+
+        // the same units can be chosen again
+        let action = multi_target_action(None, DuplicateTargets::Allowed, enemies(2));
+        assert!(!action.is_full(&fighters));
+    }
+}
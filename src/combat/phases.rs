@@ -3,9 +3,10 @@ use rand::Rng;
 
 use crate::{
     combat::{
-        alterations::{Alteration, AlterationAction},
-        skills::{SkillExecutionQueue, TargetOption},
-        stats::{Hp, Initiative, Mana, Shield},
+        alterations::{Alteration, AlterationAction, AlterationEnd, AlterationEndEvent},
+        skills::{random_targets, SkillExecutionQueue, TargetOption},
+        stats::{apply_modifiers, Hp, Initiative, MagicShield, Mana, Regeneration, Shield},
+        stuff::Job,
         Action, ActionCount, CombatResources, CombatState, CurrentAlterations, InCombat,
     },
     ui::{
@@ -43,7 +44,10 @@ pub fn phase_transition(
 
     mut selected_units_query: Query<Entity, (With<Selected>, With<InCombat>)>,
     targeted_unit_query: Query<(Entity, &Name), With<Targeted>>,
-    mut combat_unit_query: Query<(Entity, &mut ActionCount, &Hp, &Team), With<InCombat>>,
+    mut combat_unit_query: Query<
+        (Entity, &mut ActionCount, &Hp, &Team, &CurrentAlterations),
+        With<InCombat>,
+    >,
 
     mut actions_logs: ResMut<ActionsLogs>,
     action_history: Res<ActionHistory>,
@@ -89,7 +93,10 @@ pub fn phase_transition(
                         TargetOption::OneSelf
                         | TargetOption::AllAlly
                         | TargetOption::AllEnemy
-                        | TargetOption::All => {
+                        | TargetOption::All
+                        | TargetOption::AllButSelf
+                        | TargetOption::RandomEnemy(_)
+                        | TargetOption::RandomAlly(_) => {
                             action_count.current -= 1;
                             info!("action left: {}", action_count.current);
 
@@ -114,7 +121,7 @@ pub fn phase_transition(
                         }
                         TargetOption::AllAlly => {
                             let mut targets: Vec<Entity> = Vec::new();
-                            for (entity, _, hp, team) in combat_unit_query.iter() {
+                            for (entity, _, hp, team, _) in combat_unit_query.iter() {
                                 if hp.current > 0 && team == caster_team {
                                     targets.push(entity);
                                 }
@@ -123,7 +130,7 @@ pub fn phase_transition(
                         }
                         TargetOption::AllEnemy => {
                             let mut targets: Vec<Entity> = Vec::new();
-                            for (entity, _, hp, team) in combat_unit_query.iter() {
+                            for (entity, _, hp, team, _) in combat_unit_query.iter() {
                                 if hp.current > 0 && team != caster_team {
                                     targets.push(entity);
                                 }
@@ -132,13 +139,40 @@ pub fn phase_transition(
                         }
                         TargetOption::All => {
                             let mut targets: Vec<Entity> = Vec::new();
-                            for (entity, _, hp, _) in combat_unit_query.iter() {
+                            for (entity, _, hp, _, _) in combat_unit_query.iter() {
                                 if hp.current > 0 {
                                     targets.push(entity);
                                 }
                             }
                             last_action.targets = Some(targets);
                         }
+                        TargetOption::AllButSelf => {
+                            let mut targets: Vec<Entity> = Vec::new();
+                            for (entity, _, hp, _, _) in combat_unit_query.iter() {
+                                if hp.current > 0 && entity != last_action.caster {
+                                    targets.push(entity);
+                                }
+                            }
+                            last_action.targets = Some(targets);
+                        }
+                        TargetOption::RandomEnemy(number) | TargetOption::RandomAlly(number) => {
+                            let enemy_side = matches!(
+                                last_action.skill.target_option,
+                                TargetOption::RandomEnemy(_)
+                            );
+                            let candidates: Vec<Entity> = combat_unit_query
+                                .iter()
+                                .filter(|(_, _, hp, team, _)| {
+                                    hp.current > 0 && (**team != *caster_team) == enemy_side
+                                })
+                                .map(|(entity, ..)| entity)
+                                .collect();
+                            last_action.targets = Some(random_targets(
+                                &candidates,
+                                number,
+                                last_action.skill.duplicate_targets,
+                            ));
+                        }
                         _ => {}
                     }
                 } else {
@@ -180,6 +214,21 @@ pub fn phase_transition(
                  */
             }
 
+            /* -------------------------------------------------------------------------- */
+            /*                              Formation Editor                              */
+            /* -------------------------------------------------------------------------- */
+            (CombatState::Initialisation, CombatState::SelectionCaster) => {
+                // the formation is already written in each `TacticalPosition`
+                // REFACTOR: Move these ui lines somewhere else -> [[combat::phases::phase_transition()]]
+                actions_logs
+                    .0
+                    .push_str("\n---------------\nFormation confirmed\n");
+            }
+            (CombatState::Initialisation, _) => {
+                warn!("The formation must be confirmed before the first turn");
+                continue;
+            }
+
             /* -------------------------------------------------------------------------- */
             /*                                 End of Turn                                */
             /* -------------------------------------------------------------------------- */
@@ -235,8 +284,12 @@ pub fn phase_transition(
                 combat_resources.history = Vec::new();
 
                 // Reset all ActionCounter/Limit
-                for (_, mut action_count, _, _) in combat_unit_query.iter_mut() {
-                    action_count.current = action_count.base;
+                for (_, mut action_count, _, _, alterations) in combat_unit_query.iter_mut() {
+                    action_count.current = if alterations.contains_action(AlterationAction::Stun) {
+                        0
+                    } else {
+                        action_count.base
+                    };
                 }
             }
             _ => {}
@@ -275,84 +328,119 @@ pub fn phase_transition(
 
 // TODO: ShouldHave - Visual - Display mutable change (dmg, heal) (on the field)
 
-/// # Note
+/// Regenerate each fighter's resources w.r.t. their `Regeneration` and `Job`,
+/// then execute all their alterations.
 ///
-/// DOC
+/// An alteration ticks exactly `duration` times,
+/// and expires at the following tick (see `Alteration`'s lifecycle).
 pub fn execute_alteration(
     mut character_query: Query<(
+        Entity,
         &mut Hp,
         &mut Mana,
         &mut Shield,
+        &mut MagicShield,
         &mut CurrentAlterations,
+        &Regeneration,
+        &Job,
         &Name,
     )>,
 
     mut transition_phase_event: EventWriter<TransitionPhaseEvent>,
+    mut alteration_end_event: EventWriter<AlterationEndEvent>,
+    mut actions_logs: ResMut<ActionsLogs>,
 ) {
-    for (mut hp, mut mp, mut shield, mut alterations, name) in character_query.iter_mut() {
+    for (
+        character,
+        mut hp,
+        mut mp,
+        mut shield,
+        mut magic_shield,
+        mut alterations,
+        regeneration,
+        job,
+        name,
+    ) in character_query.iter_mut()
+    {
+        // ---- Regeneration ----
+        // The knockout don't regenerate
+        if hp.current > 0 {
+            mp.current =
+                (mp.current + regeneration.mana + job.mana_regeneration()).clamp(0, mp.max);
+            shield.0 -= shield.0 * regeneration.shield_decay / 100;
+            magic_shield.0 -= magic_shield.0 * regeneration.shield_decay / 100;
+        }
+
         let mut new_alterations_vector: Vec<Alteration> = Vec::new();
-        for alteration in alterations.iter_mut() {
-            info!("DEBUG: Execute Alteration: {} on {}", alteration.name, name);
-            // info!(
-            //     "duration/turnCount - {}/{}",
-            //     alteration.duration, alteration.turn_count
-            // );
-            if alteration.duration > 0 {
-                alteration.duration -= 1;
-                alteration.turn_count += 1;
-                info!(
-                    "DEBUG: new duration: {}", // /{}
-                    alteration.duration        //, alteration.turn_count
-                );
-                new_alterations_vector.push(alteration.clone());
+        for mut alteration in std::mem::take(&mut alterations.0) {
+            // ---- OnExpire ----
+            // the auras last as long as their source (see `tactical_position::update_auras()`)
+            if alteration.duration <= 0 && !alteration.aura {
+                info!("DEBUG: {} expired on {}", alteration.name, name);
+                alteration_end_event.send(AlterationEndEvent {
+                    bearer: character,
+                    alteration,
+                    end: AlterationEnd::Expired,
+                });
+                continue;
             }
 
-            match alteration.action {
-                AlterationAction::StatsFlat | AlterationAction::StatsPercentage => {
-                    // occurs only the first time
-                    if alteration.turn_count != 0 {
-                        continue;
-                    }
-                }
-                _ => {}
-            }
+            // ---- OnTick ----
+            info!("DEBUG: Execute Alteration: {} on {}", alteration.name, name);
+            let hp_before = hp.current;
             match alteration.action {
                 AlterationAction::Dots => {
-                    hp.current += alteration.hp;
-                    mp.current += alteration.mana;
-                    shield.0 += alteration.shield;
+                    hp.current += alteration.scaled(alteration.hp);
+                    mp.current += alteration.scaled(alteration.mana);
+                    // shields can decay but not below 0
+                    shield.0 = (shield.0 + alteration.shield).max(0);
+                    magic_shield.0 = (magic_shield.0 + alteration.magic_shield).max(0);
                 }
-                // The action of StatsPercentage will not trigger if its tunn_count != 0
-                AlterationAction::StatsPercentage | AlterationAction::PercentageAsDots => {
-                    if alteration.hp != 0 {
-                        hp.current *= alteration.hp;
-                    }
-                    if alteration.mana != 0 {
-                        mp.current *= alteration.mana;
-                    }
-                    if alteration.shield != 0 {
-                        shield.0 *= alteration.shield;
-                    }
-                    // if alteration.initiative != 0 {
-                    //     initiative.0 *= alteration.initiative;
-                    // }
-                    if alteration.turn_count != 0 {
-                        // At each turn, we increment/decrement the alteration's stats
-                        // ----- EX: +10% attack/turn -----
-                        // t0: alt.attck = 10;  When the alteration is inserted
-                        // t1: 10 + 10/1;       When the first altPhase occurs
-                        // ...
-                        // t5: 10 + 10/1 + 20/2 + 30/3 + 40/4;
-                        alteration.attack += alteration.attack / alteration.turn_count;
-                        alteration.attack_spe += alteration.attack_spe / alteration.turn_count;
-                        alteration.defense += alteration.defense / alteration.turn_count;
-                        alteration.defense_spe += alteration.defense_spe / alteration.turn_count;
-                    }
+                AlterationAction::PercentageAsDots => {
+                    alteration.tick_percentage(&mut hp, &mut mp, &mut shield, &mut magic_shield);
                 }
-                AlterationAction::StatsFlat => {
+                AlterationAction::StatsFlat | AlterationAction::StatsPercentage => {
                     // no action, the alteration being still in the entity contains all the info.
+                    // (see `stats::apply_modifiers()`)
+                }
+                AlterationAction::Mute
+                | AlterationAction::ForcePass
+                | AlterationAction::Stun
+                | AlterationAction::Taunt
+                | AlterationAction::Root => {
+                    // no action, checked by the skill selection and execution
                 }
             }
+
+            let hp_change = hp.current - hp_before;
+            if hp_change > 0 {
+                actions_logs.0.push_str(&format!(
+                    "\n{} heals {} for {}",
+                    alteration.attribution(),
+                    name,
+                    hp_change
+                ));
+            } else if hp_change < 0 {
+                actions_logs.0.push_str(&format!(
+                    "\n{} deals {} to {}",
+                    alteration.attribution(),
+                    -hp_change,
+                    name
+                ));
+            }
+
+            if !alteration.aura {
+                alteration.duration -= 1;
+            }
+            alteration.turn_count += 1;
+            if alteration.escalating {
+                alteration.escalate();
+            }
+            // info!(
+            //     "duration/turnCount - {}/{}",
+            //     alteration.duration, alteration.turn_count
+            // );
+            new_alterations_vector.push(alteration);
         }
         // update the set of alteration
         alterations.0 = new_alterations_vector;
@@ -361,6 +449,56 @@ pub fn execute_alteration(
     transition_phase_event.send(TransitionPhaseEvent(CombatState::default()));
 }
 
+/// Remove the channeled alterations of the newly knocked out sources
+/// (see `Alteration::channeled`).
+pub fn break_channeled_alterations(
+    knocked_out_query: Query<(Entity, &Hp), (Changed<Hp>, With<InCombat>)>,
+    mut character_query: Query<(Entity, &mut CurrentAlterations, &Name), With<InCombat>>,
+
+    mut alteration_end_event: EventWriter<AlterationEndEvent>,
+    mut actions_logs: ResMut<ActionsLogs>,
+) {
+    let knocked_out: Vec<Entity> = knocked_out_query
+        .iter()
+        .filter(|(_, hp)| hp.current <= 0)
+        .map(|(entity, _)| entity)
+        .collect();
+    if knocked_out.is_empty() {
+        return;
+    }
+
+    for (character, mut alterations, name) in character_query.iter_mut() {
+        let is_broken = |alteration: &Alteration| {
+            alteration.channeled
+                && alteration
+                    .source
+                    .as_ref()
+                    .map_or(false, |source| knocked_out.contains(&source.entity))
+        };
+        if !alterations.iter().any(is_broken) {
+            continue;
+        }
+
+        let (broken, kept): (Vec<Alteration>, Vec<Alteration>) = std::mem::take(&mut alterations.0)
+            .into_iter()
+            .partition(is_broken);
+        alterations.0 = kept;
+
+        for alteration in broken {
+            actions_logs.0.push_str(&format!(
+                "\n{} on {} is broken",
+                alteration.attribution(),
+                name
+            ));
+            alteration_end_event.send(AlterationEndEvent {
+                bearer: character,
+                alteration,
+                end: AlterationEnd::Removed,
+            });
+        }
+    }
+}
+
 /// Roll for each entity a d100 ranged into +-20 initiative
 /// Also Display the final score
 ///
@@ -382,20 +520,15 @@ pub fn roll_initiative(
         match combat_units_query.get(caster) {
             Err(e) => warn!("Invalid Caster are in the History: {}", e),
             Ok((base_init, alterations)) => {
-                let mut current_init = base_init.0;
-
                 // ---- Alterations Rules ----
-                for alteration in alterations.iter() {
-                    match alteration.action {
-                        AlterationAction::StatsFlat => {
-                            current_init += alteration.initiative;
-                        }
-                        _ => {}
-                    }
-                }
+                let current_init =
+                    apply_modifiers(base_init.0, alterations, |alteration| alteration.initiative);
+
                 // ---- Calculus ----
 
-                // BUG: When applying multiple times Swiftness can lead to an empty range
+                // The alterations' stacking policies cap the bonus,
+                // but keep the range valid whatever happens
+                let current_init = current_init.clamp(0, 100);
 
                 let calculated_init = if current_init - 20 <= 0 {
                     rand::thread_rng().gen_range(0..current_init + 20)
@@ -446,16 +579,19 @@ pub fn execution_phase(
     mut skill_execution_queue: ResMut<SkillExecutionQueue>,
     mut transition_phase_event: EventWriter<TransitionPhaseEvent>,
 ) {
+    // triggered since the last execution (e.g. a death by dot), resolved first
+    let pending_skills = std::mem::take(&mut skill_execution_queue.queue);
+
     // The result will be pushed into a vector and processed last to first
-    let mut action_history = combat_resources.history.clone();
-    action_history.reverse();
-
-    for Action {
-        caster,
-        skill,
-        targets,
-        initiative: _,
-    } in action_history.iter()
+    for (
+        action,
+        Action {
+            caster,
+            skill,
+            targets,
+            initiative: _,
+        },
+    ) in combat_resources.history.iter().enumerate().rev()
     {
         match targets {
             None => warn!(
@@ -463,6 +599,8 @@ pub fn execution_phase(
                 caster, skill
             ),
             Some(targets) => {
+                // The area of effect is computed around each final target
+                // (see `skills::execute_skill()`)
                 for target in targets {
                     // we will do a verification anyway (skill's hp_cost)
                     // in the event handler
@@ -471,6 +609,10 @@ pub fn execution_phase(
                         skill: skill.clone(),
                         caster: *caster,
                         target: *target,
+                        triggered: false,
+                        action: Some(action),
+                        action_targets: targets.clone(),
+                        action_splashed: Vec::new(),
                     });
 
                     // should be in order
@@ -480,12 +622,17 @@ pub fn execution_phase(
                             caster: *caster,
                             // All skills in the queue will be directed to the same target
                             target: *target,
+                            triggered: false,
+                            action: Some(action),
+                            action_targets: targets.clone(),
+                            action_splashed: Vec::new(),
                         });
                     }
                 }
             }
         }
     }
+    skill_execution_queue.extend(pending_skills);
 
     transition_phase_event.send(TransitionPhaseEvent(CombatState::ExecuteSkills));
 }
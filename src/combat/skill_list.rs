@@ -3,9 +3,12 @@
 //! We call `spell`, technic that indivuals have regardless of their stuff
 //! We call `skill`, technic given by using a certain weapon
 
-use crate::combat::skills::{Skill, SkillType, TargetOption};
+use crate::combat::skills::{
+    AlterationTarget, AreaOfEffect, Dispel, Displacement, DuplicateTargets, ManaEffect, Movement,
+    RetargetPolicy, Skill, SkillRange, SkillType, TargetOption,
+};
 
-use super::alterations::Alteration;
+use super::alterations::{Alteration, AlterationNature, TriggerEffect};
 
 impl Skill {
     /// TOTEST: Maybe don't allow multiple ways to pass: select all pass or click EndOfTurn ? - Force to press EndOfTurn
@@ -24,6 +27,7 @@ impl Skill {
         Skill {
             skill_type: SkillType::Attack,
             target_option: TargetOption::Enemy(1),
+            retarget: RetargetPolicy::Nearest,
             initiative: 50,
             hp_dealt: 50,
             description: String::from("Deal 50 dmg"),
@@ -45,20 +49,109 @@ impl Skill {
         }
     }
 
+    /// Is a spell
+    pub fn ward() -> Self {
+        Skill {
+            skill_type: SkillType::DefenseSpe,
+            target_option: TargetOption::Ally(1),
+            initiative: 55,
+            mana_cost: 15,
+            shield_dealt: 30,
+            alterations: vec![(AlterationTarget::Target, Alteration::fading_ward())],
+            description: String::from("Give 30magic shield which fades away"),
+            name: String::from("Ward"),
+            ..Default::default()
+        }
+    }
+
+    /// Is a spell
+    ///
+    /// Cleanse the 2 most recent debuffs of an ally
+    pub fn purify() -> Self {
+        Skill {
+            skill_type: SkillType::Buff,
+            target_option: TargetOption::Ally(1),
+            initiative: 65,
+            mana_cost: 15,
+            dispel: Some(Dispel {
+                count: 2,
+                nature: Some(AlterationNature::Debuff),
+                school: None,
+            }),
+            description: String::from("Remove 2 debuffs from an ally"),
+            name: String::from("Purify"),
+            ..Default::default()
+        }
+    }
+
+    /// Is a spell
+    ///
+    /// Dispel the most recent buff of an enemy
+    pub fn disenchant() -> Self {
+        Skill {
+            skill_type: SkillType::Debuff,
+            target_option: TargetOption::Enemy(1),
+            initiative: 45,
+            mana_cost: 10,
+            dispel: Some(Dispel {
+                count: 1,
+                nature: Some(AlterationNature::Buff),
+                school: None,
+            }),
+            description: String::from("Remove a buff from an enemy"),
+            name: String::from("Disenchant"),
+            ..Default::default()
+        }
+    }
+
+    /// Is a spell
+    pub fn bramble() -> Self {
+        Skill {
+            skill_type: SkillType::Buff,
+            target_option: TargetOption::Ally(1),
+            initiative: 50,
+            mana_cost: 20,
+            alterations: vec![(AlterationTarget::Target, Alteration::thorns())],
+            description: String::from("Cover an ally with thorns"),
+            name: String::from("Bramble"),
+            ..Default::default()
+        }
+    }
+
+    /// Is a spell
+    pub fn entangle() -> Self {
+        Skill {
+            skill_type: SkillType::Debuff,
+            target_option: TargetOption::Enemy(1),
+            initiative: 60,
+            mana_cost: 20,
+            alterations: vec![(AlterationTarget::Target, Alteration::rooted())],
+            description: String::from("Root an enemy in place for 2 turns"),
+            name: String::from("Entangle"),
+            ..Default::default()
+        }
+    }
+
     pub fn gifle() -> Self {
         Skill {
             skill_type: SkillType::Attack,
             target_option: TargetOption::Enemy(1),
+            range: SkillRange::Melee,
             initiative: 70,
             // Immediate
             hp_dealt: 1,
-            alterations: vec![Alteration::honte()],
+            alterations: vec![
+                (AlterationTarget::Target, Alteration::honte()),
+                (AlterationTarget::Caster, Alteration::anger()),
+            ],
             description: String::from("Frappe Vile qui inflige le débuff Honte"),
             name: String::from("Gifle"),
             ..Default::default()
         }
     }
 
+    /// The enemy gets angry: a buff which can be dispelled,
+    /// traded for the mana drained.
     pub fn diffamation() -> Self {
         Skill {
             skill_type: SkillType::Debuff,
@@ -66,26 +159,178 @@ impl Skill {
             initiative: 75,
             mana_cost: 20,
             mana_dealt: 30,
-            alterations: vec![Alteration::anger()],
-            description: String::from("Diffamation Politiquement Correcte"),
+            mana_effect: ManaEffect::Drain,
+            alterations: vec![(AlterationTarget::Target, Alteration::anger())],
+            description: String::from("Drain 30 mana from an enemy, who gets angry"),
             name: String::from("Diffamation"),
             ..Default::default()
         }
     }
 
+    /// Strip the shield before hitting (and hit harder if there was any)
+    pub fn crush() -> Self {
+        Skill {
+            skill_type: SkillType::ShieldBreaker,
+            target_option: TargetOption::Enemy(1),
+            range: SkillRange::Melee,
+            initiative: 40,
+            hp_dealt: 20,
+            shield_dealt: -50,
+            description: String::from(
+                "Break 50shield then deal 20 dmg (+50% if the target was shielded)",
+            ),
+            name: String::from("Crush"),
+            ..Default::default()
+        }
+    }
+
+    pub fn headbutt() -> Self {
+        Skill {
+            skill_type: SkillType::Attack,
+            target_option: TargetOption::Enemy(1),
+            range: SkillRange::Melee,
+            initiative: 20,
+            hp_dealt: 20,
+            alterations: vec![(AlterationTarget::Target, Alteration::stun())],
+            description: String::from("Deal 20 dmg and stun the enemy for 1 turn"),
+            name: String::from("Headbutt"),
+            ..Default::default()
+        }
+    }
+
+    /// Push the target one line back
+    pub fn shove() -> Self {
+        Skill {
+            skill_type: SkillType::Attack,
+            target_option: TargetOption::Enemy(1),
+            range: SkillRange::Melee,
+            initiative: 45,
+            hp_dealt: 15,
+            displacement: Some(Displacement {
+                movement: Movement::Backward,
+                collision_damage: 15,
+            }),
+            description: String::from(
+                "Deal 15 dmg and push the enemy back (+15 dmg if something is in the way)",
+            ),
+            name: String::from("Shove"),
+            ..Default::default()
+        }
+    }
+
+    /// Pull the target one line forward
+    pub fn hook() -> Self {
+        Skill {
+            skill_type: SkillType::Attack,
+            target_option: TargetOption::Enemy(1),
+            initiative: 35,
+            hp_dealt: 10,
+            displacement: Some(Displacement {
+                movement: Movement::Forward,
+                collision_damage: 0,
+            }),
+            description: String::from("Deal 10 dmg and pull the enemy forward"),
+            name: String::from("Hook"),
+            ..Default::default()
+        }
+    }
+
+    /// Swap the target with their ally directly behind them
+    pub fn shuffle() -> Self {
+        Skill {
+            skill_type: SkillType::Debuff,
+            target_option: TargetOption::Enemy(1),
+            initiative: 40,
+            mana_cost: 10,
+            displacement: Some(Displacement {
+                movement: Movement::Swap,
+                collision_damage: 0,
+            }),
+            description: String::from("Swap an enemy with the one behind them"),
+            name: String::from("Shuffle"),
+            ..Default::default()
+        }
+    }
+
     /// `Deal 25dmg to 3targets` (example of multi-targets skills)
     pub fn implosion() -> Self {
         Skill {
             skill_type: SkillType::AttackSpe,
             target_option: TargetOption::Enemy(3),
+            duplicate_targets: DuplicateTargets::Forbidden,
             initiative: 25,
             hp_dealt: 50,
-            description: String::from("Deal 25 dmg to 3 enemies"),
+            description: String::from("Deal 25 dmg to 3 different enemies"),
             name: String::from("Implosion"),
             ..Default::default()
         }
     }
 
+    pub fn ricochet() -> Self {
+        Skill {
+            skill_type: SkillType::Attack,
+            target_option: TargetOption::RandomEnemy(3),
+            retarget: RetargetPolicy::Random,
+            initiative: 40,
+            hp_dealt: 20,
+            description: String::from(
+                "Deal 20 dmg to 3 random enemies (one may be hit several times)",
+            ),
+            name: String::from("Ricochet"),
+            ..Default::default()
+        }
+    }
+
+    pub fn cacophony() -> Self {
+        Skill {
+            skill_type: SkillType::AttackSpe,
+            target_option: TargetOption::AllButSelf,
+            initiative: 20,
+            mana_cost: 30,
+            hp_dealt: 30,
+            description: String::from("Deal 30 dmg to everyone else, allies included"),
+            name: String::from("Cacophony"),
+            ..Default::default()
+        }
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                  Movements                                 */
+    /* -------------------------------------------------------------------------- */
+
+    pub fn swap() -> Self {
+        Skill {
+            skill_type: SkillType::Move(Movement::Swap),
+            target_option: TargetOption::AllyButSelf(1),
+            initiative: 50,
+            description: String::from("Exchange your position with an ally"),
+            name: String::from("Swap"),
+            ..Default::default()
+        }
+    }
+
+    pub fn advance() -> Self {
+        Skill {
+            skill_type: SkillType::Move(Movement::Forward),
+            target_option: TargetOption::OneSelf,
+            initiative: 50,
+            description: String::from("Move one line toward the enemies"),
+            name: String::from("Advance"),
+            ..Default::default()
+        }
+    }
+
+    pub fn retreat() -> Self {
+        Skill {
+            skill_type: SkillType::Move(Movement::Backward),
+            target_option: TargetOption::OneSelf,
+            initiative: 50,
+            description: String::from("Move one line away from the enemies"),
+            name: String::from("Retreat"),
+            ..Default::default()
+        }
+    }
+
     /* -------------------------------------------------------------------------- */
     /*                               Weapons' Skills                              */
     /* -------------------------------------------------------------------------- */
@@ -95,9 +340,13 @@ impl Skill {
         Skill {
             skill_type: SkillType::Attack,
             target_option: TargetOption::Enemy(1),
+            area: AreaOfEffect::Adjacent,
+            splash: 50,
             initiative: 30,
             hp_dealt: 60,
-            description: String::from("Explode 60 dmg to 1 enemies"),
+            description: String::from(
+                "Explode 60 dmg to 1 enemy and half of it to the ones next to them",
+            ),
             name: String::from("SelfDestruct of the Pickles Jar"),
             ..Default::default()
         }
@@ -109,7 +358,7 @@ impl Skill {
             target_option: TargetOption::OneSelf,
             initiative: 60,
             hp_dealt: 25,
-            alterations: vec![Alteration::regenerate()],
+            alterations: vec![(AlterationTarget::Target, Alteration::regenerate())],
             description: String::from("Heal 25Hp and add Regenerate"),
             name: String::from("Open the jar and eat a pickle"),
             ..Default::default()
@@ -124,7 +373,7 @@ impl Skill {
             initiative: 60,
             mana_cost: 20,
             shield_dealt: 10,
-            alterations: vec![Alteration::swiftness()],
+            alterations: vec![(AlterationTarget::Target, Alteration::swiftness())],
             description: String::from("Up the initative of allies and give Shield"),
             name: String::from("Melody"),
             ..Default::default()
@@ -134,12 +383,13 @@ impl Skill {
     pub fn swing() -> Self {
         Skill {
             skill_type: SkillType::AttackSpe,
-            target_option: TargetOption::Enemy(3),
+            target_option: TargetOption::Enemy(1),
+            range: SkillRange::Reach,
+            area: AreaOfEffect::Line,
             initiative: 60,
-            // TODO: feature - placement, here NEAR
             mana_cost: 25,
             hp_dealt: 25,
-            description: String::from("Slash Near enemies with a hard bass wave"),
+            description: String::from("Slash a whole line of enemies with a hard bass wave"),
             name: String::from("Swing"),
             ..Default::default()
         }
@@ -152,8 +402,11 @@ impl Skill {
             initiative: 35,
             mana_cost: 25,
             shield_dealt: 25,
-            // TODO: alteration -> up % that enemies will target the cursed
-            alterations: vec![Alteration::hardness()],
+            alterations: vec![
+                (AlterationTarget::Caster, Alteration::hardness()),
+                (AlterationTarget::Caster, Alteration::provoke()),
+                (AlterationTarget::Caster, Alteration::riposte()),
+            ],
             description: String::from("Give yourself a medium shield and buff your physical defense, focus yourself to aggro"),
             name: String::from("Solo"),
             ..Default::default()
@@ -168,7 +421,7 @@ impl Skill {
             initiative: 15,
             hp_dealt: 15,
             shield_dealt: 5,
-            alterations: vec![Alteration::stale_odour()],
+            alterations: vec![(AlterationTarget::Caster, Alteration::stale_odour())],
             description: String::from("Give yourself a small shield and heals you a medium amount"),
             name: String::from("Eat The Fish"),
             ..Default::default()
@@ -182,7 +435,7 @@ impl Skill {
             target_option: TargetOption::Enemy(1),
             initiative: 30,
             hp_dealt: 15,
-            alterations: vec![Alteration::stale_odour()],
+            alterations: vec![(AlterationTarget::Target, Alteration::stale_odour())],
             description: String::from("Throw a jelly stinky fish to your enemy"),
             name: String::from("Throw The Fish"),
             ..Default::default()
@@ -191,12 +444,14 @@ impl Skill {
 
     pub fn fish_slam() -> Self {
         Skill {
-            skill_type: SkillType::Heal,
+            skill_type: SkillType::Attack,
             target_option: TargetOption::Enemy(2),
+            min_targets: Some(1),
+            range: SkillRange::Melee,
             initiative: 55,
             hp_dealt: 25,
-            alterations: vec![Alteration::stale_odour()],
-            description: String::from("Slam two near enemies with power"),
+            alterations: vec![(AlterationTarget::Target, Alteration::stale_odour())],
+            description: String::from("Slam up to two near enemies with power"),
             name: String::from("Fish Slam"),
             ..Default::default()
         }
@@ -208,10 +463,54 @@ impl Skill {
             target_option: TargetOption::OneSelf,
             initiative: 60,
             mana_cost: 30,
-            alterations: vec![Alteration::swiftness()],
+            alterations: vec![(AlterationTarget::Caster, Alteration::swiftness())],
             description: String::from("Give yourself a small shield and heals you a medium amount"),
             name: String::from("Surf"),
             ..Default::default()
         }
     }
+
+    /* -------------------------------------------------------------------------- */
+    /*                              Triggered Skills                              */
+    /* -------------------------------------------------------------------------- */
+
+    /// The skill performed by the bearer of an alteration when its `Trigger` occurs.
+    ///
+    /// `damage`: the hp lost during the triggering skill
+    pub fn triggered(effect: &TriggerEffect, damage: i32) -> Self {
+        match effect {
+            TriggerEffect::Thorns(percentage) => Skill {
+                skill_type: SkillType::TrueDamage,
+                target_option: TargetOption::Enemy(1),
+                hp_dealt: damage * percentage / 100,
+                description: format!("Reflect {}% of the damage suffered", percentage),
+                name: String::from("Thorns"),
+                ..Default::default()
+            },
+            TriggerEffect::Lifesteal(percentage) => Skill {
+                skill_type: SkillType::Heal,
+                target_option: TargetOption::OneSelf,
+                hp_dealt: damage * percentage / 100,
+                description: format!("Heal {}% of the damage dealt", percentage),
+                name: String::from("Lifesteal"),
+                ..Default::default()
+            },
+            TriggerEffect::Explode(hp_dealt) => Skill {
+                skill_type: SkillType::AttackSpe,
+                target_option: TargetOption::AllEnemy,
+                hp_dealt: *hp_dealt,
+                description: format!("Deal {} dmg to all enemies", hp_dealt),
+                name: String::from("Explosion"),
+                ..Default::default()
+            },
+            TriggerEffect::Counterattack(hp_dealt) => Skill {
+                skill_type: SkillType::TrueDamage,
+                target_option: TargetOption::Enemy(1),
+                hp_dealt: *hp_dealt,
+                description: format!("Deal {} dmg to the attacker", hp_dealt),
+                name: String::from("Counterattack"),
+                ..Default::default()
+            },
+        }
+    }
 }
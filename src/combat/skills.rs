@@ -2,19 +2,30 @@
 
 use bevy::prelude::*;
 use bevy_ecs::query::QueryEntityError;
+use rand::seq::SliceRandom;
 // use bevy_inspector_egui::prelude::*;
 
 use crate::{
     combat::{
         alterations::*,
-        stats::{Attack, AttackSpe, Defense, DefenseSpe, Hp, Mana, Shield},
+        stats::{
+            absorb_damage, apply_modifiers, break_shield, consume_shield, damage_reduction,
+            percentage_of, ArmorPenetration, Attack, AttackSpe, Defense, DefenseSpe, Hp,
+            MagicShield, Mana, Shield,
+        },
+        stuff::{Equipements, WeaponType},
     },
     constants::combat::skill::*,
     spritesheet::SpriteSheetIndex,
     ui::combat_system::ActionsLogs,
 };
 
-use super::CurrentAlterations;
+use super::{
+    tactical_position::{
+        depth, is_covered, position_damage_modifier, MoveFighterEvent, PositionModifiers,
+    },
+    CurrentAlterations, InCombat, TacticalPosition, Team,
+};
 
 #[derive(Default, Debug, Clone, PartialEq, Reflect)]
 pub enum SkillType {
@@ -22,6 +33,10 @@ pub enum SkillType {
     Attack,
     AttackSpe,
     ShieldBreaker,
+    /// Deal exactly `hp_dealt`, whatever the multipliers, the armors or the shields
+    ///
+    /// Used by the triggered skills which return an amount (see `Skill::triggered()`)
+    TrueDamage,
     Defense,
     DefenseSpe,
     Buff,
@@ -29,6 +44,79 @@ pub enum SkillType {
     #[default]
     Pass,
     Flee,
+    /// Change the caster's `TacticalPosition`
+    Move(Movement),
+}
+
+/// How a fighter's `TacticalPosition` changes
+///
+/// - The caster's, by a `SkillType::Move`
+/// - The target's, by a `Displacement`
+///
+/// See `tactical_position::move_fighters()`
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum Movement {
+    /// Exchange the positions of the fighter and an ally
+    ///
+    /// - `SkillType::Move`: the target ally
+    /// - `Displacement`: the target's ally directly behind them
+    #[default]
+    Swap,
+    /// One line toward the enemies, in the same place
+    Forward,
+    /// One line away from the enemies, in the same place
+    Backward,
+}
+
+/// Forced movement of the skill's target, if they survive the hit
+///
+/// The lines are w.r.t. the target's team:
+/// - Push: `Movement::Backward`
+/// - Pull: `Movement::Forward`
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct Displacement {
+    pub movement: Movement,
+    /// Damage dealt to the target, and to the ally in their way,
+    /// if the movement is blocked (by an ally or the edge of the grid)
+    pub collision_damage: i32,
+}
+
+/// What happens to the mana removed from the target (`Skill::mana_dealt`)
+#[derive(Default, Debug, Clone, PartialEq, Reflect)]
+pub enum ManaEffect {
+    /// The mana is just lost
+    #[default]
+    Consume,
+    /// The caster gains what the target loses
+    Drain,
+    /// Deal x% of the mana destroyed as bonus hp damage
+    ///
+    /// This bonus damage ignores any shield.
+    Burn(i32),
+}
+
+/// Remove up to `count` alterations of the target, most recent first,
+/// matching the filter.
+///
+/// - Cleanse: remove the `AlterationNature::Debuff` of an ally
+/// - Dispel: remove the `AlterationNature::Buff` of an enemy
+#[derive(Default, Debug, Clone, PartialEq, Reflect)]
+pub struct Dispel {
+    pub count: usize,
+    /// `None` for any nature
+    pub nature: Option<AlterationNature>,
+    /// `None` for any school
+    pub school: Option<AlterationSchool>,
+}
+
+impl Dispel {
+    pub fn matches(&self, alteration: &Alteration) -> bool {
+        self.nature
+            .map_or(true, |nature| nature == alteration.nature)
+            && self
+                .school
+                .map_or(true, |school| Some(school) == alteration.school)
+    }
 }
 
 /// # Note
@@ -50,7 +138,171 @@ pub enum TargetOption {
     /// The skill affects one by one target
     AllEnemy,
     All,
-    // IDEA: Any(usize) ?
+    /// Any units, from either side (the identity included)
+    Any(usize),
+    /// All units, from either side, except the identity
+    AllButSelf,
+    /// Enemies picked at random when the action is built (see `random_targets()`)
+    RandomEnemy(usize),
+    /// Allies, the identity included, picked at random when the action is built
+    /// (see `random_targets()`)
+    RandomAlly(usize),
+}
+
+impl TargetOption {
+    /// The maximum number of targets chosen one by one (by the player or picked at random)
+    ///
+    /// `None` for the options targeting a whole group (or the identity)
+    pub fn max_targets(&self) -> Option<usize> {
+        match self {
+            TargetOption::Enemy(number)
+            | TargetOption::Ally(number)
+            | TargetOption::AllyButSelf(number)
+            | TargetOption::Any(number)
+            | TargetOption::RandomEnemy(number)
+            | TargetOption::RandomAlly(number) => Some(*number),
+            TargetOption::OneSelf
+            | TargetOption::AllAlly
+            | TargetOption::AllEnemy
+            | TargetOption::All
+            | TargetOption::AllButSelf => None,
+        }
+    }
+}
+
+/// Can a skill hit the same unit several times, among its targets
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum DuplicateTargets {
+    /// The same unit can be chosen again
+    #[default]
+    Allowed,
+    /// Each target is a different unit
+    Forbidden,
+}
+
+/// `number` targets picked at random among the `candidates`.
+///
+/// With `DuplicateTargets::Forbidden`, there are fewer targets
+/// if there are fewer candidates.
+///
+/// # Note
+///
+/// Used by `TargetOption::RandomEnemy` and `RandomAlly`,
+/// and by the AI for every n-target option.
+/// The caller filters the candidates: only the alive ones,
+/// in the skill's range and w.r.t. the Taunt if they apply.
+pub fn random_targets(
+    candidates: &[Entity],
+    number: usize,
+    duplicates: DuplicateTargets,
+) -> Vec<Entity> {
+    let mut rng = rand::thread_rng();
+    match duplicates {
+        DuplicateTargets::Allowed => (0..number)
+            .filter_map(|_| candidates.choose(&mut rng).copied())
+            .collect(),
+        DuplicateTargets::Forbidden => candidates
+            .choose_multiple(&mut rng, number)
+            .copied()
+            .collect(),
+    }
+}
+
+/// What happens to a queued execution whose target is knocked out before it resolves
+///
+/// The new target is an alive ally of the knocked out one,
+/// reachable by the skill (see `SkillRange`).
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum RetargetPolicy {
+    /// The execution is lost
+    #[default]
+    Fizzle,
+    /// Redirected to any valid target
+    Random,
+    /// Redirected to the valid target the closest to the knocked out one
+    /// (see `TacticalPosition::distance()`)
+    Nearest,
+}
+
+/// How far an enemy can be reached by a skill, w.r.t. the `TacticalPosition`s
+///
+/// See `tactical_position::depth()`
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum SkillRange {
+    /// From the caster's front line to the enemies' front line
+    Melee,
+    /// One line further than `Melee`
+    Reach,
+    /// Anywhere
+    #[default]
+    Ranged,
+}
+
+impl SkillRange {
+    /// Both depths are the number of lines, holding someone alive,
+    /// in front of the fighter in their own team.
+    pub fn reaches(&self, caster_depth: usize, target_depth: usize) -> bool {
+        let distance = caster_depth + target_depth;
+        match self {
+            SkillRange::Melee => distance == 0,
+            SkillRange::Reach => distance <= 1,
+            SkillRange::Ranged => true,
+        }
+    }
+}
+
+/// Units, around each primary target, also hit by the skill
+///
+/// The area covers the 3x3 grid of the primary target's team.
+/// The primary targets are chosen as usual, through the `TargetOption`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum AreaOfEffect {
+    /// Only the primary target
+    #[default]
+    Single,
+    /// The whole line of the primary target
+    Line,
+    /// The whole column (same `TacticalPlace`) of the primary target
+    Column,
+    /// The whole line and the whole column of the primary target
+    Cross,
+    /// The units next to the primary target (no diagonal)
+    Adjacent,
+}
+
+impl AreaOfEffect {
+    /// Is the `position` hit by an area centered on the `primary` target
+    ///
+    /// The primary target's position is always covered.
+    pub fn covers(&self, primary: &TacticalPosition, position: &TacticalPosition) -> bool {
+        if primary == position {
+            return true;
+        }
+
+        let same_line = primary.line() == position.line();
+        let same_column = primary.place() == position.place();
+        match self {
+            AreaOfEffect::Single => false,
+            AreaOfEffect::Line => same_line,
+            AreaOfEffect::Column => same_column,
+            AreaOfEffect::Cross => same_line || same_column,
+            AreaOfEffect::Adjacent => primary.is_adjacent_to(position),
+        }
+    }
+}
+
+/// Who receives an alteration given by a skill
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum AlterationTarget {
+    /// The skill's target
+    #[default]
+    Target,
+    /// The one casting the skill
+    Caster,
+    /// All the caster's allies, the caster excluded
+    CasterAllies,
+    /// All the target's allies, the target excluded
+    TargetAllies,
 }
 
 /// Endure every stats to the target
@@ -67,8 +319,27 @@ pub struct Skill {
     /// - target all enemy party: TargetOption::AllEnemy
     /// - self-target: TargetOption::OneSelf
     /// - targeted heal: TargetOption::Ally(1)
-    /// - small explosion: TargetOption::Enemy(1) (but with aoe: true)
+    /// - small explosion: TargetOption::Enemy(1) (with `area: AreaOfEffect::Adjacent`)
     pub target_option: TargetOption,
+    /// Number of targets required to confirm a partial selection
+    /// (see `ui::player_interaction::confirm_targets()`)
+    ///
+    /// `None`: the maximum of the `TargetOption`.
+    /// Never more than the units which can still be targeted.
+    pub min_targets: Option<usize>,
+    /// Can the same unit be chosen several times
+    pub duplicate_targets: DuplicateTargets,
+    /// If a target is knocked out before the execution
+    pub retarget: RetargetPolicy,
+    /// How far the enemies targeted can be
+    pub range: SkillRange,
+    /// The other units hit around each target
+    pub area: AreaOfEffect,
+    /// Percentage of the effects (hp, mana and shield dealt)
+    /// endured by the units in the area, other than the targets.
+    ///
+    /// 100 = no falloff
+    pub splash: i32,
     /// Wait for the turn delay to execute
     ///
     /// # Note
@@ -83,14 +354,16 @@ pub struct Skill {
     pub hp_dealt: i32,
     /// mana: consume/gain to the target
     pub mana_dealt: i32,
+    /// Drain or Burn the mana consumed to the target
+    pub mana_effect: ManaEffect,
     /// shield: reduce/addition to the target
     ///
-    /// # Note
+    /// - Negative = the amount of shield stripped (`SkillType::ShieldBreaker`)
+    /// - Positive = the amount of shield given (`SkillType::Defense`, `Buff` or `Heal`)
     ///
-    /// Can direct
+    /// # Note
     ///
-    /// - a attack to only target shield
-    /// - a bonus to regenerate/add shield
+    /// A `SkillType::DefenseSpe` gives/removes `MagicShield` instead.
     pub shield_dealt: i32,
     /// Self-inflicted Dmg
     ///
@@ -100,9 +373,24 @@ pub struct Skill {
     pub hp_cost: i32,
     /// The Skill's Mana cost
     pub mana_cost: i32,
-    // TODO: feature - shield cost ?
-    /// Debuff or Buff
-    pub alterations: Vec<Alteration>,
+    /// The Skill's Shield cost
+    ///
+    /// Consume the caster's shield (without going below 0)
+    pub shield_cost: i32,
+    /// Flat amount of the target's Defense/DefenseSpe ignored.
+    ///
+    /// Added to the caster's (and their weapon's) `ArmorPenetration`
+    pub armor_penetration: i32,
+    /// Remove some alterations of the target (before giving the new ones)
+    pub dispel: Option<Dispel>,
+    /// Move the target after the hit
+    pub displacement: Option<Displacement>,
+    /// Debuff or Buff, and who receives them (never the knocked out fighters)
+    ///
+    /// # Example
+    ///
+    /// Gifle: Honte to the target, Anger to the caster
+    pub alterations: Vec<(AlterationTarget, Alteration)>,
     /// The 'list' of skills called after this one
     ///
     /// # Note
@@ -120,13 +408,24 @@ impl Default for Skill {
         Skill {
             skill_type: Default::default(),
             target_option: TargetOption::OneSelf,
+            min_targets: None,
+            duplicate_targets: DuplicateTargets::default(),
+            retarget: RetargetPolicy::default(),
+            range: SkillRange::default(),
+            area: AreaOfEffect::default(),
+            splash: 100,
             turn_delay: 0,
             initiative: 0,
             hp_dealt: 0,
             hp_cost: 0,
             mana_dealt: 0,
+            mana_effect: ManaEffect::Consume,
             mana_cost: 0,
+            shield_cost: 0,
+            armor_penetration: 0,
             shield_dealt: 0,
+            dispel: None,
+            displacement: None,
             alterations: vec![],
             skills_queue: vec![],
             description: String::from("..."),
@@ -137,6 +436,65 @@ impl Default for Skill {
     }
 }
 
+impl Skill {
+    /// Does the skill aim to harm its target
+    pub fn is_harmful(&self) -> bool {
+        matches!(
+            self.skill_type,
+            SkillType::Attack
+                | SkillType::AttackSpe
+                | SkillType::ShieldBreaker
+                | SkillType::TrueDamage
+                | SkillType::Debuff
+        )
+    }
+
+    /// The skill endured by the units in the area, other than the targets
+    ///
+    /// Its effects are reduced to `splash`% and its costs are only paid
+    /// for the targets. Only the alterations given to the target are kept.
+    pub fn splashed(&self) -> Skill {
+        Skill {
+            area: AreaOfEffect::Single,
+            retarget: RetargetPolicy::Fizzle,
+            hp_dealt: percentage_of(self.hp_dealt, self.splash),
+            mana_dealt: percentage_of(self.mana_dealt, self.splash),
+            shield_dealt: percentage_of(self.shield_dealt, self.splash),
+            hp_cost: 0,
+            mana_cost: 0,
+            shield_cost: 0,
+            alterations: self
+                .alterations
+                .iter()
+                .filter(|(receiver, _)| *receiver == AlterationTarget::Target)
+                .cloned()
+                .collect(),
+            skills_queue: vec![],
+            ..self.clone()
+        }
+    }
+
+    /// Is the skill allowed by the control alterations of its caster
+    ///
+    /// - `AlterationAction::Stun`: no skill at all
+    /// - `AlterationAction::ForcePass`: only `SkillType::Pass`
+    /// - `AlterationAction::Mute`: no skill which costs mana
+    /// - `AlterationAction::Root`: no `SkillType::Move`
+    pub fn is_allowed(&self, caster_alterations: &CurrentAlterations) -> bool {
+        if caster_alterations.contains_action(AlterationAction::Stun) {
+            false
+        } else if caster_alterations.contains_action(AlterationAction::ForcePass) {
+            self.skill_type == SkillType::Pass
+        } else if matches!(self.skill_type, SkillType::Move(_))
+            && caster_alterations.contains_action(AlterationAction::Root)
+        {
+            false
+        } else {
+            !(self.mana_cost > 0 && caster_alterations.contains_action(AlterationAction::Mute))
+        }
+    }
+}
+
 /// Happens in
 ///   - combat::phases::execution_phase
 ///     - The skill animation ended, the last one in the queue
@@ -161,6 +519,20 @@ pub struct SkillToExecute {
     pub skill: Skill,
     pub caster: Entity,
     pub target: Entity,
+    /// Performed by a triggered alteration (see `Skill::triggered()`)
+    /// or endured around a target (see `Skill::splashed()`)
+    ///
+    /// Can't be prevented by control alterations
+    /// and doesn't trigger anything but deaths.
+    pub triggered: bool,
+    /// Index of its action in `CombatResources::history`,
+    /// `None` for the triggered and splashed skills
+    pub action: Option<usize>,
+    /// All the targets of the action, never splashed (see `Skill::area`)
+    pub action_targets: Vec<Entity>,
+    /// The units already splashed by this skill of the action,
+    /// not splashed again around the other targets
+    pub action_splashed: Vec<Entity>,
 }
 
 /// Execution of the skill queue to all entity targeted
@@ -183,29 +555,211 @@ pub fn execute_skill(
         &mut Hp,
         &mut Mana,
         &mut Shield,
+        &mut MagicShield,
         &Attack,
         &AttackSpe,
         &Defense,
         &DefenseSpe,
+        &ArmorPenetration,
+        &Equipements,
+        &Immunities,
         &mut CurrentAlterations,
         &Name,
     )>,
+    weapon_query: Query<&ArmorPenetration, With<WeaponType>>,
+    fighters_query: Query<(Entity, &Team), With<InCombat>>,
+    positions_query: Query<(Entity, &Team, &TacticalPosition), With<InCombat>>,
+    position_modifiers: Res<PositionModifiers>,
     mut actions_logs: ResMut<ActionsLogs>,
+    mut alteration_end_event: EventWriter<AlterationEndEvent>,
+    mut move_fighter_event: EventWriter<MoveFighterEvent>,
 ) {
     for ExecuteSkillEvent in execute_skill_event.iter() {
         let SkillToExecute {
             skill,
             caster,
             target,
+            triggered,
+            action,
+            action_targets,
+            action_splashed,
         } = skill_execution_queue.pop().unwrap();
 
+        // the knocked out targets are already redirected (see `retarget_knocked_out_targets()`)
+        let is_alive = |fighter: Entity| {
+            combat_unit
+                .get(fighter)
+                .map_or(false, |(hp, ..)| hp.current > 0)
+        };
+
+        // the triggered skills only hit the alive units (see `trigger()`)
+        let mut alive_fighters: Vec<Entity> = fighters_query
+            .iter()
+            .map(|(fighter, _)| fighter)
+            .filter(|fighter| is_alive(*fighter))
+            .collect();
+
+        // ---- Area of Effect ----
+        // The alive units of the target's team around the final target,
+        // each one only once per action (the splashed skill has no area itself)
+        let splashed_units: Vec<Entity> = match positions_query.get(target) {
+            Ok((_, target_team, target_position)) if skill.area != AreaOfEffect::Single => {
+                positions_query
+                    .iter()
+                    .filter(|(unit, team, position)| {
+                        *team == target_team
+                            && *unit != target
+                            && !action_targets.contains(unit)
+                            && !action_splashed.contains(unit)
+                            && alive_fighters.contains(unit)
+                            && skill.area.covers(target_position, position)
+                    })
+                    .map(|(unit, ..)| unit)
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        // applied once the caster and target are released
+        let mut given_alterations: Vec<(Entity, Alteration)> = Vec::new();
+
+        let position_modifier = match (positions_query.get(caster), positions_query.get(target)) {
+            (Ok((_, _, caster_position)), Ok((_, target_team, target_position))) => {
+                let target_team_positions: Vec<TacticalPosition> = positions_query
+                    .iter()
+                    .filter(|(fighter, team, _)| {
+                        *team == target_team
+                            && combat_unit
+                                .get(*fighter)
+                                .map_or(false, |(hp, ..)| hp.current > 0)
+                    })
+                    .map(|(_, _, position)| *position)
+                    .collect();
+                position_damage_modifier(
+                    &position_modifiers,
+                    &skill,
+                    caster_position,
+                    target_position,
+                    is_covered(target_position, &target_team_positions),
+                )
+            }
+            _ => 0,
+        };
+
         match combat_unit.get_many_mut([caster, target]) {
             // REFACTOR: Handle SelfCast
             Err(e) => {
                 match e {
                     // SelfCast
+                    // REFACTOR: Only the beneficial part of the skill is handled
                     QueryEntityError::AliasedMutability(_) => {
-                        warn!("TODO: SelfCast is currently not implemented  {:?}", e)
+                        let Ok((
+                            mut hp,
+                            mut mp,
+                            mut shield,
+                            mut magic_shield,
+                            _,
+                            attack_spe,
+                            ..,
+                            mut alterations,
+                            name,
+                        )) = combat_unit.get_mut(caster)
+                        else {
+                            continue;
+                        };
+
+                        if !triggered && !skill.is_allowed(&alterations) {
+                            actions_logs.0.push_str(&format!(
+                                "\n  - {} is controlled and can't use {}",
+                                name, skill.name
+                            ));
+                            continue;
+                        }
+
+                        actions_logs
+                            .0
+                            .push_str(&format!("\n- {}, from {} to themself", skill.name, name));
+
+                        // ---- COST ----
+                        hp.current -= skill.hp_cost;
+                        mp.current -= skill.mana_cost;
+                        consume_shield(&mut shield.0, skill.shield_cost);
+
+                        if hp.current <= 0 {
+                            actions_logs
+                                .0
+                                .push_str(&format!("\n  - Caster is knocked out: {}", name));
+                            continue;
+                        }
+
+                        match skill.skill_type {
+                            SkillType::Heal => {
+                                let mut heal_multiplier: f32 = 100.;
+                                for alt in alterations.iter() {
+                                    heal_multiplier +=
+                                        (alt.heal_inflicted + alt.heal_received) as f32;
+                                }
+                                let hp_healed = heal(
+                                    &mut hp,
+                                    (skill.hp_dealt as f32 * heal_multiplier / 100.) as i32,
+                                );
+                                if hp_healed > 0 {
+                                    actions_logs
+                                        .0
+                                        .push_str(&format!("\n  - hp healed: {}", hp_healed));
+                                }
+
+                                if skill.shield_dealt > 0 {
+                                    shield.0 += skill.shield_dealt;
+                                }
+                            }
+                            SkillType::Defense | SkillType::Buff => {
+                                shield.0 = (shield.0 + skill.shield_dealt).max(0);
+                            }
+                            SkillType::DefenseSpe => {
+                                magic_shield.0 = (magic_shield.0 + skill.shield_dealt).max(0);
+                            }
+                            SkillType::Pass => {}
+                            SkillType::Move(movement) => {
+                                move_fighter_event.send(MoveFighterEvent {
+                                    fighter: caster,
+                                    partner: None,
+                                    movement,
+                                    collision_damage: 0,
+                                });
+                            }
+                            _ => warn!(
+                                "TODO: SelfCast is currently not implemented for {:?}",
+                                skill.skill_type
+                            ),
+                        }
+
+                        // ---- Alterations ----
+
+                        if let Some(dispel) = &skill.dispel {
+                            for removed in alterations.remove_matching(dispel) {
+                                actions_logs
+                                    .0
+                                    .push_str(&format!("\n  - {} removed", removed.name));
+                                alteration_end_event.send(AlterationEndEvent {
+                                    bearer: caster,
+                                    alteration: removed,
+                                    end: AlterationEnd::Removed,
+                                });
+                            }
+                        }
+                        let source = alteration_source(caster, name, attack_spe, &alterations);
+                        for (alteration_target, mut alteration) in skill.alterations {
+                            alteration.source = Some(source.clone());
+                            for receiver in alteration_receivers(
+                                alteration_target,
+                                caster,
+                                target,
+                                &fighters_query,
+                            ) {
+                                given_alterations.push((receiver, alteration.clone()));
+                            }
+                        }
                     }
                     _ => warn!("Caster and/or Target Invalid {:?}", e),
                 }
@@ -215,20 +769,28 @@ pub fn execute_skill(
                     mut caster_hp,
                     mut caster_mp,
                     mut caster_shield,
+                    _caster_magic_shield,
                     caster_attack,
                     caster_attack_spe,
                     _caster_defense,
                     _caster_defense_spe,
+                    caster_penetration,
+                    caster_equipements,
+                    _caster_immunities,
                     caster_alterations,
                     caster_name,
                 ), (
                     mut target_hp,
                     mut target_mp,
                     mut target_shield,
+                    mut target_magic_shield,
                     _target_attack,
                     _target_attack_spe,
                     target_defense,
                     target_defense_spe,
+                    _target_penetration,
+                    _target_equipements,
+                    _target_immunities,
                     mut target_alterations,
                     target_name,
                 )],
@@ -248,6 +810,18 @@ pub fn execute_skill(
 
                 let skill_executed = &skill;
 
+                // ---- CONTROL ----
+
+                // The caster might have been controlled earlier this turn
+                // (a triggered skill can't be prevented)
+                if !triggered && !skill_executed.is_allowed(&caster_alterations) {
+                    actions_logs.0.push_str(&format!(
+                        "\n  - {} is controlled and can't use {}",
+                        caster_name, skill_executed.name
+                    ));
+                    continue;
+                }
+
                 // TODO: PostDemo - turn delay?
 
                 // ---- COST ----
@@ -259,10 +833,11 @@ pub fn execute_skill(
                 // TODO: PostDemo - feature - reduce cost by stuff and level
                 caster_hp.current -= skill_executed.hp_cost;
                 caster_mp.current -= skill_executed.mana_cost;
-                caster_shield.0 -= skill_executed.shield_dealt;
+                consume_shield(&mut caster_shield.0, skill_executed.shield_cost);
 
                 // don't execute the rest if the current of the caster is < 0
-                if caster_hp.current <= 0 {
+                // (except for triggered skills: a knocked out can explode)
+                if caster_hp.current <= 0 && !triggered {
                     if caster_hp.current + skill_executed.hp_cost <= 0 {
                         actions_logs
                             .0
@@ -278,6 +853,30 @@ pub fn execute_skill(
                     continue;
                 }
 
+                // the other executions of the action won't splash them again
+                for queued in skill_execution_queue.iter_mut().filter(|queued| {
+                    action.is_some()
+                        && queued.action == action
+                        && queued.skill.name == skill_executed.name
+                }) {
+                    queued
+                        .action_splashed
+                        .extend(splashed_units.iter().copied());
+                }
+                // executed right after the target,
+                // as triggered skills: they don't trigger anything but deaths
+                for unit in splashed_units {
+                    skill_execution_queue.push(SkillToExecute {
+                        skill: skill_executed.splashed(),
+                        caster,
+                        target: unit,
+                        triggered: true,
+                        action: None,
+                        action_targets: Vec::new(),
+                        action_splashed: Vec::new(),
+                    });
+                }
+
                 // if the skill is pre alteration
                 // ---- Alterations ----
 
@@ -285,54 +884,93 @@ pub fn execute_skill(
 
                 // ---- Multipliers ----
 
-                let mut attack_multiplier: f32 = 100.;
-                let mut attack_spe_multiplier: f32 = 100.;
-                let mut defense_multiplier: f32 = 100.;
-                let mut defense_spe_multiplier: f32 = 100.;
+                // (caster_attack + caster_alt_att_flat) * caster_alt_att_percent%
+                let attack_multiplier: f32 = 100.
+                    + apply_modifiers(caster_attack.base, &caster_alterations, |alteration| {
+                        alteration.attack
+                    }) as f32;
+                let attack_spe_multiplier: f32 = 100.
+                    + apply_modifiers(caster_attack_spe.base, &caster_alterations, |alteration| {
+                        alteration.attack_spe
+                    }) as f32;
                 let mut damage_multiplier: f32 = 100.;
                 let mut heal_multiplier: f32 = 100.;
 
+                // skill + caster + caster's weapon
+                let penetration = skill_executed.armor_penetration
+                    + caster_penetration.0
+                    + caster_equipements
+                        .weapon
+                        .and_then(|weapon| weapon_query.get(weapon).ok())
+                        .map_or(0, |weapon_penetration| weapon_penetration.0);
+
                 for alt in target_alterations.iter() {
-                    defense_multiplier += alt.defense as f32;
-                    defense_spe_multiplier += alt.defense_spe as f32;
                     damage_multiplier += alt.damage_suffered as f32;
                     heal_multiplier += alt.heal_received as f32;
                 }
                 for alt in caster_alterations.iter() {
-                    attack_multiplier += alt.attack as f32;
-                    attack_spe_multiplier += alt.attack_spe as f32;
                     // REFACTOR: if damage_inflicted <= -100% should be 0 dmg (even if dmg_suffered > 0)
                     damage_multiplier += alt.damage_inflicted as f32;
                     heal_multiplier += alt.heal_inflicted as f32;
                 }
+                // only for the skills dealing damage
+                if position_modifier != 0 {
+                    damage_multiplier += position_modifier as f32;
+                    actions_logs
+                        .0
+                        .push_str(&format!("\n  - position: {:+}% dmg", position_modifier));
+                }
+
+                let target_hp_before = target_hp.current;
 
                 match skill_executed.skill_type {
                     SkillType::Heal => {
                         // IDEA: no multiplier ? based on attackspe?
 
-                        // Can't revive with a Heal
+                        // round to the bottom (to i32)
+                        let hp_healed = heal(
+                            &mut target_hp,
+                            (skill_executed.hp_dealt as f32 * heal_multiplier / 100.) as i32,
+                        );
+                        if hp_healed > 0 {
+                            info!("hp healed: {}", hp_healed);
+                            actions_logs
+                                .0
+                                .push_str(&format!("\n  - hp healed: {}", hp_healed));
+                        }
 
-                        if target_hp.current < 0 {
-                            // round to the bottom (to i32)
-                            target_hp.current +=
-                                (skill_executed.hp_dealt as f32 * heal_multiplier / 100.) as i32;
-                            if target_hp.current > target_hp.max {
-                                target_hp.current = target_hp.max;
-                            }
+                        if skill_executed.shield_dealt > 0 {
+                            target_shield.0 += skill_executed.shield_dealt;
                         }
                     }
-                    SkillType::Attack => {
-                        // REFACTOR: the calculus of entity's stats in the skill execution
-                        // here having 10 attack is quite inefficent
-                        attack_multiplier += caster_attack.base as f32;
-                        defense_multiplier += target_defense.base as f32;
+                    SkillType::Attack | SkillType::ShieldBreaker => {
+                        // ---- Shield Breaker ----
+                        // strip the shield before the hit, and hit harder if there was any
+                        if skill_executed.skill_type == SkillType::ShieldBreaker {
+                            let (shield_stripped, bonus_damage) =
+                                break_shield(&mut target_shield.0, skill_executed.shield_dealt);
+                            damage_multiplier += bonus_damage as f32;
+                            if shield_stripped > 0 {
+                                info!("shield stripped: {}", shield_stripped);
+                                actions_logs.0.push_str(&format!(
+                                    "\n  - shield stripped: {}",
+                                    shield_stripped
+                                ));
+                            }
+                        }
 
-                        // x * (caster_attack + caster_alt_att)% / (target_defense + target_alt_def)% * (caster_alt_dmg_inflicted - target_alt_dmg_suffered)%
+                        let target_armor = apply_modifiers(
+                            target_defense.base,
+                            &target_alterations,
+                            |alteration| alteration.defense,
+                        ) - penetration;
+
+                        // x * (caster_attack + caster_alt_att)% * (1 - armor ÷ (armor + 100)) * (caster_alt_dmg_inflicted - target_alt_dmg_suffered)%
                         // round to the bottom (i32)
                         let hp_dealt = (skill_executed.hp_dealt as f32
                             * (attack_multiplier / 100.)
                             * (damage_multiplier / 100.)
-                            / (defense_multiplier / 100.))
+                            * (1. - damage_reduction(target_armor)))
                             as i32;
                         if hp_dealt > 0 {
                             info!("hp dealt: {}", hp_dealt);
@@ -341,41 +979,31 @@ pub fn execute_skill(
                                 .push_str(&format!("\n  - hp dealt: {}", hp_dealt));
                         }
 
-                        // ---- MP ----
-                        // x + x*(caster_attack_spe)%
-                        let mp_dealt = skill_executed.mana_dealt;
-                        if mp_dealt > 0 {
-                            info!("mp dealt: {}", mp_dealt);
-                            actions_logs
-                                .0
-                                .push_str(&format!("\n  - mp dealt: {}", mp_dealt));
-                        }
-
                         // ---- EXECUTION ----
-                        if target_shield.0 < hp_dealt {
-                            target_hp.current -= hp_dealt - target_shield.0;
-                            target_shield.0 = 0;
-                        } else {
-                            // the shield fully tank the attack
-                            target_shield.0 -= hp_dealt;
-                        }
+                        let (mana_lost, mana_burnt) = deal_mana(
+                            skill_executed.mana_dealt,
+                            &skill_executed.mana_effect,
+                            &mut caster_mp,
+                            &mut target_mp,
+                        );
+                        log_mana_lost(mana_lost, &mut actions_logs);
+                        target_hp.current -=
+                            absorb_damage(&mut target_shield.0, hp_dealt) + mana_burnt;
                         // neagtive hp allowed
-
-                        target_hp.current -= mp_dealt;
-                        if target_mp.current < 0 {
-                            target_mp.current = 0
-                        }
                     }
                     SkillType::AttackSpe => {
-                        attack_spe_multiplier += caster_attack_spe.base as f32;
-                        defense_spe_multiplier += target_defense_spe.base as f32;
+                        let target_magic_resistance = apply_modifiers(
+                            target_defense_spe.base,
+                            &target_alterations,
+                            |alteration| alteration.defense_spe,
+                        ) - penetration;
 
                         // ---- HP ----
-                        // x * (caster_att_spe + caster_alt_att_spe)% / (target_def_spe + target_alt_def_spe)% * (caster_alt_dmg_inflicted - target_alt_dmg_suffered)%
+                        // x * (caster_att_spe + caster_alt_att_spe)% * (1 - MR ÷ (MR + 100)) * (caster_alt_dmg_inflicted - target_alt_dmg_suffered)%
                         let hp_dealt = (skill_executed.hp_dealt as f32
                             * (attack_spe_multiplier / 100.)
                             * (damage_multiplier / 100.)
-                            / (defense_spe_multiplier / 100.))
+                            * (1. - damage_reduction(target_magic_resistance)))
                             as i32;
                         if hp_dealt > 0 {
                             info!("hp dealt: {}", hp_dealt);
@@ -388,31 +1016,53 @@ pub fn execute_skill(
                         // x + x*(caster_attack_spe)%
                         let mp_dealt = (skill_executed.mana_dealt as f32 * attack_spe_multiplier
                             / 100.) as i32;
-                        if mp_dealt > 0 {
-                            info!("mp dealt: {}", mp_dealt);
-                            actions_logs
-                                .0
-                                .push_str(&format!("\n  - mp dealt: {}", mp_dealt));
-                        }
 
                         // ---- EXECUTION ----
-                        target_hp.current -= hp_dealt;
+                        let (mana_lost, mana_burnt) = deal_mana(
+                            mp_dealt,
+                            &skill_executed.mana_effect,
+                            &mut caster_mp,
+                            &mut target_mp,
+                        );
+                        log_mana_lost(mana_lost, &mut actions_logs);
+                        target_hp.current -=
+                            absorb_damage(&mut target_magic_shield.0, hp_dealt) + mana_burnt;
                         // neagtive hp allowed
-
-                        target_mp.current -= mp_dealt;
-                        if target_mp.current < 0 {
-                            target_mp.current = 0
+                    }
+                    SkillType::TrueDamage => {
+                        let hp_dealt = skill_executed.hp_dealt.max(0);
+                        if hp_dealt > 0 {
+                            info!("hp dealt: {}", hp_dealt);
+                            actions_logs
+                                .0
+                                .push_str(&format!("\n  - hp dealt: {}", hp_dealt));
                         }
+                        target_hp.current -= hp_dealt;
+                    }
+                    SkillType::Debuff => {
+                        // ---- EXECUTION ----
+                        let (mana_lost, mana_burnt) = deal_mana(
+                            skill_executed.mana_dealt,
+                            &skill_executed.mana_effect,
+                            &mut caster_mp,
+                            &mut target_mp,
+                        );
+                        log_mana_lost(mana_lost, &mut actions_logs);
+                        target_hp.current -= mana_burnt;
                     }
                     // shield_dealt is neagtive when harmfull or positive when bonus
-                    SkillType::ShieldBreaker | SkillType::Defense => {
+                    SkillType::Defense | SkillType::Buff => {
                         target_shield.0 += skill_executed.shield_dealt;
                         if target_shield.0 < 0 {
                             target_shield.0 = 0
                         }
                     }
+                    // shield_dealt is neagtive when harmfull or positive when bonus
                     SkillType::DefenseSpe => {
-                        // TODO: Magic Shield
+                        target_magic_shield.0 += skill_executed.shield_dealt;
+                        if target_magic_shield.0 < 0 {
+                            target_magic_shield.0 = 0
+                        }
                     }
                     SkillType::Pass => {
                         // force action: Pass to the target next turn
@@ -420,14 +1070,560 @@ pub fn execute_skill(
 
                         // atm: an blank action
                     }
+                    SkillType::Move(movement) => {
+                        move_fighter_event.send(MoveFighterEvent {
+                            fighter: caster,
+                            partner: Some(target),
+                            movement,
+                            collision_damage: 0,
+                        });
+                    }
                     _ => {}
                 }
 
+                // ---- Triggers ----
+                // Triggered skills don't trigger anything (no infinite thorns ping-pong)
+                // The deaths are handled by `detect_knock_outs()`
+
+                let damage = target_hp_before - target_hp.current;
+                let mut triggered_skills: Vec<SkillToExecute> = Vec::new();
+                if target_hp.current <= 0 {
+                    alive_fighters.retain(|fighter| *fighter != target);
+                }
+                let is_alive = |fighter: Entity| alive_fighters.contains(&fighter);
+
+                if !triggered {
+                    if damage > 0 {
+                        triggered_skills.extend(trigger(
+                            caster_alterations.triggered_effects(Trigger::OnHit),
+                            caster,
+                            target,
+                            damage,
+                            &fighters_query,
+                            is_alive,
+                        ));
+                        triggered_skills.extend(trigger(
+                            target_alterations.triggered_effects(Trigger::OnDamaged),
+                            target,
+                            caster,
+                            damage,
+                            &fighters_query,
+                            is_alive,
+                        ));
+                    }
+                    if skill_executed.is_harmful() && target_hp.current > 0 {
+                        triggered_skills.extend(trigger(
+                            target_alterations.triggered_effects(Trigger::OnTargeted),
+                            target,
+                            caster,
+                            damage,
+                            &fighters_query,
+                            is_alive,
+                        ));
+                    }
+                }
+                // executed right after this one (LIFO)
+                skill_execution_queue.extend(triggered_skills);
+
+                // ---- Displacement ----
+
+                if let Some(displacement) = skill_executed.displacement {
+                    if target_hp.current > 0 {
+                        move_fighter_event.send(MoveFighterEvent {
+                            fighter: target,
+                            partner: None,
+                            movement: displacement.movement,
+                            collision_damage: displacement.collision_damage,
+                        });
+                    }
+                }
+
                 // if the skill is post alteration
                 // ---- Alterations ----
 
-                target_alterations.extend(skill.clone().alterations);
+                if let Some(dispel) = &skill_executed.dispel {
+                    for removed in target_alterations.remove_matching(dispel) {
+                        info!("{} removed from {}", removed.name, target_name);
+                        actions_logs
+                            .0
+                            .push_str(&format!("\n  - {} removed", removed.name));
+                        alteration_end_event.send(AlterationEndEvent {
+                            bearer: target,
+                            alteration: removed,
+                            end: AlterationEnd::Removed,
+                        });
+                    }
+                }
+
+                let source =
+                    alteration_source(caster, caster_name, caster_attack_spe, &caster_alterations);
+                for (alteration_target, mut alteration) in skill.clone().alterations {
+                    alteration.source = Some(source.clone());
+                    for receiver in
+                        alteration_receivers(alteration_target, caster, target, &fighters_query)
+                    {
+                        given_alterations.push((receiver, alteration.clone()));
+                    }
+                }
+            }
+        }
+
+        for (receiver, alteration) in given_alterations {
+            let Ok((hp, .., immunities, mut alterations, name)) = combat_unit.get_mut(receiver)
+            else {
+                continue;
+            };
+            if hp.current <= 0 {
+                continue;
+            }
+
+            match immunities.resist(alteration.clone()) {
+                None => {
+                    info!("{} is immune to {}", name, alteration.name);
+                    actions_logs
+                        .0
+                        .push_str(&format!("\n  - {} is immune to {}", name, alteration.name));
+                }
+                Some(resisted_alteration)
+                    if resisted_alteration.duration <= 0 && alteration.duration > 0 =>
+                {
+                    actions_logs
+                        .0
+                        .push_str(&format!("\n  - {} resists {}", name, alteration.name));
+                }
+                Some(resisted_alteration) => {
+                    if resisted_alteration.duration < alteration.duration {
+                        actions_logs.0.push_str(&format!(
+                            "\n  - {} resists {} ({} turns)",
+                            name, alteration.name, resisted_alteration.duration
+                        ));
+                    }
+                    for evicted in alterations.insert(resisted_alteration) {
+                        actions_logs
+                            .0
+                            .push_str(&format!("\n  - {}'s {} replaced", name, evicted.name));
+                        alteration_end_event.send(AlterationEndEvent {
+                            bearer: receiver,
+                            alteration: evicted,
+                            end: AlterationEnd::Removed,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Fighters receiving an alteration given by a skill
+fn alteration_receivers(
+    alteration_target: AlterationTarget,
+    caster: Entity,
+    target: Entity,
+    fighters_query: &Query<(Entity, &Team), With<InCombat>>,
+) -> Vec<Entity> {
+    let allies_of = |fighter: Entity| -> Vec<Entity> {
+        let Ok((_, fighter_team)) = fighters_query.get(fighter) else {
+            return Vec::new();
+        };
+        fighters_query
+            .iter()
+            .filter(|(ally, team)| *ally != fighter && *team == fighter_team)
+            .map(|(ally, _)| ally)
+            .collect()
+    };
+
+    match alteration_target {
+        AlterationTarget::Target => vec![target],
+        AlterationTarget::Caster => vec![caster],
+        AlterationTarget::CasterAllies => allies_of(caster),
+        AlterationTarget::TargetAllies => allies_of(target),
+    }
+}
+
+/// Redirect the next execution if its target was knocked out earlier this turn,
+/// before its VFX is played (see `fx::add_skill_vfx()`).
+///
+/// A dead caster is handled with the cost (see `execute_skill()`),
+/// and an execution which can't be redirected fizzles.
+pub fn retarget_knocked_out_targets(
+    mut skill_execution_queue: ResMut<SkillExecutionQueue>,
+    fighters_query: Query<(&Hp, &CurrentAlterations, &Name), With<InCombat>>,
+    positions_query: Query<(Entity, &Team, &TacticalPosition), With<InCombat>>,
+    mut actions_logs: ResMut<ActionsLogs>,
+) {
+    let is_alive = |fighter: Entity| {
+        fighters_query
+            .get(fighter)
+            .map_or(false, |(hp, ..)| hp.current > 0)
+    };
+    let is_taunting = |fighter: Entity| {
+        fighters_query
+            .get(fighter)
+            .map_or(false, |(_, alterations, _)| {
+                alterations.contains_action(AlterationAction::Taunt)
+            })
+    };
+    let name_of = |fighter: Entity| {
+        fighters_query
+            .get(fighter)
+            .map_or(String::from("?"), |(.., name)| name.to_string())
+    };
+
+    while let Some(SkillToExecute {
+        skill,
+        caster,
+        target,
+        triggered,
+        action,
+        action_targets,
+        ..
+    }) = skill_execution_queue.last().cloned()
+    {
+        if target == caster || is_alive(target) || !(triggered || is_alive(caster)) {
+            return;
+        }
+
+        match retarget(
+            &skill,
+            caster,
+            target,
+            &action_targets,
+            &positions_query,
+            is_alive,
+            is_taunting,
+        ) {
+            Some(new_target) => {
+                actions_logs.0.push_str(&format!(
+                    "\n- {} is knocked out: {}'s {} is redirected to {}",
+                    name_of(target),
+                    name_of(caster),
+                    skill.name,
+                    name_of(new_target)
+                ));
+                let current = skill_execution_queue.len() - 1;
+                // the other executions of the action won't target them again
+                for queued in skill_execution_queue.queue[..current]
+                    .iter_mut()
+                    .filter(|queued| action.is_some() && queued.action == action)
+                {
+                    queued.action_targets.push(new_target);
+                }
+                let execution = &mut skill_execution_queue.queue[current];
+                execution.action_targets.push(new_target);
+                execution.target = new_target;
+                return;
+            }
+            None => {
+                actions_logs.0.push_str(&format!(
+                    "\n- {}'s {} fizzles: {} is already knocked out",
+                    name_of(caster),
+                    skill.name,
+                    name_of(target)
+                ));
+                skill_execution_queue.pop();
+            }
+        }
+    }
+}
+
+/// New target of an execution whose `target` is knocked out,
+/// according to the skill's `RetargetPolicy`
+///
+/// The same rules as the selection apply (see `ui::combat_system::update_targeted_unit()`):
+/// the skill's range, the Taunt for a single-target skill on enemies
+/// and the `DuplicateTargets` policy w.r.t. the `action_targets`.
+///
+/// `None` if the skill fizzles.
+fn retarget(
+    skill: &Skill,
+    caster: Entity,
+    target: Entity,
+    action_targets: &[Entity],
+    positions_query: &Query<(Entity, &Team, &TacticalPosition), With<InCombat>>,
+    is_alive: impl Fn(Entity) -> bool,
+    is_taunting: impl Fn(Entity) -> bool,
+) -> Option<Entity> {
+    if skill.retarget == RetargetPolicy::Fizzle {
+        return None;
+    }
+    let (_, caster_team, caster_position) = positions_query.get(caster).ok()?;
+    let (_, target_team, target_position) = positions_query.get(target).ok()?;
+
+    let alive_positions = |team: &Team| -> Vec<TacticalPosition> {
+        positions_query
+            .iter()
+            .filter(|(fighter, fighter_team, _)| *fighter_team == team && is_alive(*fighter))
+            .map(|(_, _, position)| *position)
+            .collect()
+    };
+    let caster_depth = depth(caster_position, &alive_positions(caster_team));
+    let target_team_positions = alive_positions(target_team);
+
+    let on_enemies = target_team != caster_team;
+    let taunted = on_enemies
+        && skill.target_option == TargetOption::Enemy(1)
+        && positions_query.iter().any(|(fighter, team, _)| {
+            team == target_team && is_alive(fighter) && is_taunting(fighter)
+        });
+
+    let candidates: Vec<(Entity, &TacticalPosition)> = positions_query
+        .iter()
+        .filter(|(fighter, team, position)| {
+            *fighter != target
+                && *team == target_team
+                && is_alive(*fighter)
+                && (!taunted || is_taunting(*fighter))
+                && (skill.duplicate_targets == DuplicateTargets::Allowed
+                    || !action_targets.contains(fighter))
+                && (!on_enemies
+                    || skill
+                        .range
+                        .reaches(caster_depth, depth(position, &target_team_positions)))
+        })
+        .map(|(fighter, _, position)| (fighter, position))
+        .collect();
+
+    match skill.retarget {
+        RetargetPolicy::Fizzle => None,
+        RetargetPolicy::Random => candidates
+            .choose(&mut rand::thread_rng())
+            .map(|(fighter, _)| *fighter),
+        RetargetPolicy::Nearest => candidates
+            .iter()
+            .min_by_key(|(_, position)| position.distance(target_position))
+            .map(|(fighter, _)| *fighter),
+    }
+}
+
+/// Snapshot of the caster given to the alterations they apply
+fn alteration_source(
+    caster: Entity,
+    caster_name: &Name,
+    caster_attack_spe: &AttackSpe,
+    caster_alterations: &[Alteration],
+) -> AlterationSource {
+    AlterationSource {
+        entity: caster,
+        name: caster_name.to_string(),
+        attack_spe: apply_modifiers(caster_attack_spe.base, caster_alterations, |alteration| {
+            alteration.attack_spe
+        }),
+    }
+}
+
+/// Remove (or give if negative) `mp_dealt` mana to the target,
+/// clamped between 0 and `Mana.max`.
+///
+/// - `ManaEffect::Drain`: The caster gains what the target lost (up to their `Mana.max`)
+/// - `ManaEffect::Burn`: The bonus hp damage w.r.t. the mana destroyed
+///
+/// Returns the mana really lost by the target (after the clamp)
+/// and the bonus damage (0 if there is none).
+fn deal_mana(
+    mp_dealt: i32,
+    mana_effect: &ManaEffect,
+    caster_mp: &mut Mana,
+    target_mp: &mut Mana,
+) -> (i32, i32) {
+    let mana_before = target_mp.current;
+    target_mp.current = (target_mp.current - mp_dealt).clamp(0, target_mp.max);
+    // the target might have gained some mana
+    let mana_lost = (mana_before - target_mp.current).max(0);
+
+    let bonus_damage = match mana_effect {
+        ManaEffect::Consume => 0,
+        ManaEffect::Drain => {
+            caster_mp.current = (caster_mp.current + mana_lost).min(caster_mp.max);
+            0
+        }
+        ManaEffect::Burn(percentage) => mana_lost * percentage / 100,
+    };
+
+    (mana_lost, bonus_damage)
+}
+
+/// Log the mana really removed from the target
+fn log_mana_lost(mana_lost: i32, actions_logs: &mut ActionsLogs) {
+    if mana_lost > 0 {
+        info!("mp dealt: {}", mana_lost);
+        actions_logs
+            .0
+            .push_str(&format!("\n  - mp dealt: {}", mana_lost));
+    }
+}
+
+/// Heal the target without reviving them nor exceeding their `Hp.max`
+///
+/// Returns the hp healed
+fn heal(target_hp: &mut Hp, amount: i32) -> i32 {
+    // Can't revive with a Heal
+    if target_hp.current <= 0 {
+        return 0;
+    }
+    let hp_before = target_hp.current;
+    target_hp.current = (target_hp.current + amount).min(target_hp.max);
+    target_hp.current - hp_before
+}
+
+/// Log the newly knocked out fighters and queue their `Trigger::OnDeath` effects,
+/// whatever hit them (skill, dot or collision).
+///
+/// # Note
+///
+/// A death has no triggerer (directed to the bearer) nor damage.
+pub fn detect_knock_outs(
+    knocked_out_query: Query<
+        (Entity, &Hp, &CurrentAlterations, &Name),
+        (Changed<Hp>, With<InCombat>),
+    >,
+    hp_query: Query<&Hp, With<InCombat>>,
+    fighters_query: Query<(Entity, &Team), With<InCombat>>,
+    // already knocked out, to trigger a death only once
+    mut knocked_out: Local<Vec<Entity>>,
+
+    mut skill_execution_queue: ResMut<SkillExecutionQueue>,
+    mut actions_logs: ResMut<ActionsLogs>,
+) {
+    for (fighter, hp, alterations, name) in knocked_out_query.iter() {
+        if hp.current > 0 {
+            knocked_out.retain(|unit| *unit != fighter);
+            continue;
+        }
+        if knocked_out.contains(&fighter) {
+            continue;
+        }
+        knocked_out.push(fighter);
+
+        info!("{} is knocked out", name);
+        actions_logs
+            .0
+            .push_str(&format!("\n  - {} is knocked out", name));
+
+        // executed right after the current one (LIFO)
+        skill_execution_queue.extend(trigger(
+            alterations.triggered_effects(Trigger::OnDeath),
+            fighter,
+            fighter,
+            0,
+            &fighters_query,
+            |unit| hp_query.get(unit).map_or(false, |hp| hp.current > 0),
+        ));
+    }
+}
+
+/// Build the skills performed by the `bearer` of the triggered `effects`.
+///
+/// Directed to the bearer (`TargetOption::OneSelf`),
+/// all their alive enemies (`TargetOption::AllEnemy`)
+/// or the one who triggered it (`triggerer`).
+fn trigger(
+    effects: Vec<TriggerEffect>,
+    bearer: Entity,
+    triggerer: Entity,
+    damage: i32,
+    fighters_query: &Query<(Entity, &Team), With<InCombat>>,
+    is_alive: impl Fn(Entity) -> bool,
+) -> Vec<SkillToExecute> {
+    let mut skills_to_execute = Vec::new();
+    for effect in effects.iter() {
+        let skill = Skill::triggered(effect, damage);
+        let targets = match skill.target_option {
+            TargetOption::OneSelf => vec![bearer],
+            TargetOption::AllEnemy => {
+                let bearer_team = fighters_query.get(bearer).map(|(_, team)| *team).ok();
+                fighters_query
+                    .iter()
+                    .filter(|(fighter, team)| Some(**team) != bearer_team && is_alive(*fighter))
+                    .map(|(fighter, _)| fighter)
+                    .collect()
             }
+            _ => vec![triggerer],
+        };
+        for target in targets {
+            skills_to_execute.push(SkillToExecute {
+                skill: skill.clone(),
+                caster: bearer,
+                target,
+                triggered: true,
+                action: None,
+                action_targets: Vec::new(),
+                action_splashed: Vec::new(),
+            });
         }
     }
+    skills_to_execute
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::TacticalPlace;
+
+    #[test]
+    fn melee_only_reaches_front_line_to_front_line() {
+        assert!(SkillRange::Melee.reaches(0, 0));
+        assert!(!SkillRange::Melee.reaches(0, 1));
+        assert!(!SkillRange::Melee.reaches(1, 0));
+    }
+
+    #[test]
+    fn reach_goes_one_line_further() {
+        assert!(SkillRange::Reach.reaches(0, 0));
+        assert!(SkillRange::Reach.reaches(0, 1));
+        assert!(SkillRange::Reach.reaches(1, 0));
+        assert!(!SkillRange::Reach.reaches(1, 1));
+        assert!(!SkillRange::Reach.reaches(0, 2));
+    }
+
+    #[test]
+    fn ranged_reaches_anywhere() {
+        assert!(SkillRange::Ranged.reaches(2, 2));
+    }
+
+    #[test]
+    fn melee_reaches_the_middle_line_once_the_front_line_is_empty() {
+        let caster = TacticalPosition::FrontLine(TacticalPlace::Middle);
+        let target = TacticalPosition::MiddleLine(TacticalPlace::Left);
+        let caster_depth = depth(&caster, &[caster]);
+
+        let guarded = [TacticalPosition::FrontLine(TacticalPlace::Right), target];
+        assert!(!SkillRange::Melee.reaches(caster_depth, depth(&target, &guarded)));
+
+        assert!(SkillRange::Melee.reaches(caster_depth, depth(&target, &[target])));
+    }
+
+    #[test]
+    fn single_only_covers_the_primary_target() {
+        let primary = TacticalPosition::MiddleLine(TacticalPlace::Middle);
+        assert!(AreaOfEffect::Single.covers(&primary, &primary));
+        assert!(!AreaOfEffect::Single.covers(&primary, &primary.behind().unwrap()));
+    }
+
+    #[test]
+    fn areas_cover_their_shape_around_the_primary_target() {
+        let primary = TacticalPosition::MiddleLine(TacticalPlace::Middle);
+        let same_line = TacticalPosition::MiddleLine(TacticalPlace::Left);
+        let same_column = TacticalPosition::FrontLine(TacticalPlace::Middle);
+        let diagonal = TacticalPosition::FrontLine(TacticalPlace::Left);
+        let far_column = TacticalPosition::BackLine(TacticalPlace::Middle);
+
+        assert!(AreaOfEffect::Line.covers(&primary, &same_line));
+        assert!(!AreaOfEffect::Line.covers(&primary, &same_column));
+
+        assert!(AreaOfEffect::Column.covers(&primary, &same_column));
+        assert!(AreaOfEffect::Column.covers(&primary, &far_column));
+        assert!(!AreaOfEffect::Column.covers(&primary, &same_line));
+
+        assert!(AreaOfEffect::Cross.covers(&primary, &same_line));
+        assert!(AreaOfEffect::Cross.covers(&primary, &far_column));
+        assert!(!AreaOfEffect::Cross.covers(&primary, &diagonal));
+
+        assert!(AreaOfEffect::Adjacent.covers(&primary, &same_line));
+        assert!(AreaOfEffect::Adjacent.covers(&primary, &same_column));
+        assert!(!AreaOfEffect::Adjacent.covers(&primary, &diagonal));
+        assert!(!AreaOfEffect::Adjacent.covers(
+            &TacticalPosition::FrontLine(TacticalPlace::Middle),
+            &far_column
+        ));
+    }
 }
@@ -3,7 +3,13 @@
 use bevy::prelude::*;
 // use bevy_inspector_egui::prelude::*;
 
-use crate::characters::npcs::NPC;
+use crate::{
+    characters::npcs::NPC,
+    combat::alterations::{Alteration, AlterationAction},
+    constants::combat::{
+        skill::SHIELD_BREAKER_BONUS_DAMAGE, BASE_MANA_REGENERATION, BASE_SHIELD_DECAY,
+    },
+};
 
 /// Each entity which can be involved in a combat has this Bundle
 #[derive(Bundle, Default)]
@@ -11,6 +17,7 @@ pub struct StatBundle {
     pub hp: Hp,
     pub mana: Mana,
     pub shield: Shield,
+    pub magic_shield: MagicShield,
     pub initiative: Initiative,
     pub attack: Attack,
     pub attack_spe: AttackSpe,
@@ -18,6 +25,8 @@ pub struct StatBundle {
     pub defense: Defense,
     /// Magical Resistance
     pub defense_spe: DefenseSpe,
+    pub armor_penetration: ArmorPenetration,
+    pub regeneration: Regeneration,
 }
 
 /// ----------Hp----------
@@ -102,6 +111,86 @@ impl Default for Shield {
     }
 }
 
+/// Let the shield tank the damage.
+///
+/// Returns the damage which went through the shield.
+///
+/// # Note
+///
+/// Used for both `Shield` and `MagicShield`
+pub fn absorb_damage(shield: &mut i32, damage: i32) -> i32 {
+    if damage <= 0 {
+        0
+    } else if *shield < damage {
+        let damage_through = damage - *shield;
+        *shield = 0;
+        damage_through
+    } else {
+        // the shield fully tank the attack
+        *shield -= damage;
+        0
+    }
+}
+
+/// Pay a `Skill::shield_cost`, without going below 0
+pub fn consume_shield(shield: &mut i32, cost: i32) {
+    *shield = (*shield - cost).max(0);
+}
+
+/// Strip the shield before a `SkillType::ShieldBreaker` hit.
+///
+/// Returns the shield stripped (at most `-shield_dealt`)
+/// and the bonus damage (in percentage), only if there was any shield.
+pub fn break_shield(shield: &mut i32, shield_dealt: i32) -> (i32, i32) {
+    let bonus_damage = if *shield > 0 {
+        SHIELD_BREAKER_BONUS_DAMAGE
+    } else {
+        0
+    };
+    let shield_stripped = (*shield).min(-shield_dealt).max(0);
+    *shield -= shield_stripped;
+
+    (shield_stripped, bonus_damage)
+}
+
+/// ----------Magic Shield----------
+///
+/// Start of the Game: 0-100shield -> End of the Game: 10 000shield.
+///
+/// Can be modified by level, item, buff, debuff, technics.
+///
+/// Absorb the magical damage (`SkillType::AttackSpe`)
+/// as the `Shield` absorb the physical damage (`SkillType::Attack`).
+#[derive(Component, Default, Deref, DerefMut, Reflect, Debug)]
+pub struct MagicShield(pub i32);
+
+/// ----------Regeneration----------
+///
+/// Resources restored or lost at the start of each turn
+/// (during `CombatState::AlterationsExecution`).
+///
+/// Can be modified by level, item.
+///
+/// # Note
+///
+/// The `Job` gives an additional mana regeneration (see `Job::mana_regeneration()`).
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct Regeneration {
+    /// Mana gained per turn, clamped to `Mana.max`
+    pub mana: i32,
+    /// Percentage of the `Shield` and `MagicShield` lost per turn
+    pub shield_decay: i32,
+}
+
+impl Default for Regeneration {
+    fn default() -> Self {
+        Regeneration {
+            mana: BASE_MANA_REGENERATION,
+            shield_decay: BASE_SHIELD_DECAY,
+        }
+    }
+}
+
 /// ----------Attack----------
 ///
 /// Start of the Game: 10-20 -> End of the Game: ~.
@@ -200,6 +289,66 @@ impl Default for DefenseSpe {
     }
 }
 
+/// ----------Armor Penetration----------
+///
+/// Start of the Game: 0 -> End of the Game: ~
+///
+/// Can be modified by level, item, technics.
+///
+/// Flat amount of the target's Defense or DefenseSpe ignored
+/// by every technic of the entity.
+/// A weapon's penetration is added to its owner's.
+#[derive(Component, Default, Deref, DerefMut, Debug, Clone, Reflect)]
+pub struct ArmorPenetration(pub i32);
+
+/// Percentage (between 0 and 1) of the damage reduced by the given armor.
+///
+/// Calculated by armor ÷ (armor + 100).
+///
+/// # Note
+///
+/// A negative armor (over-penetrated) doesn't amplify the damage.
+pub fn damage_reduction(armor: i32) -> f32 {
+    if armor <= 0 {
+        0.
+    } else {
+        armor as f32 / (armor as f32 + 100.)
+    }
+}
+
+/// Apply the alterations' modifiers to a base stat.
+///
+/// (base + modifier_flat) * modifer_percent%
+///
+/// # Example
+///
+/// `apply_modifiers(defense.base, &alterations, |alteration| alteration.defense)`
+pub fn apply_modifiers(
+    base: i32,
+    alterations: &[Alteration],
+    modifier: fn(&Alteration) -> i32,
+) -> i32 {
+    let mut modifier_flat = 0;
+    let mut modifier_percent = 100;
+
+    for alteration in alterations {
+        match alteration.action {
+            AlterationAction::StatsFlat => modifier_flat += modifier(alteration),
+            AlterationAction::StatsPercentage => modifier_percent += modifier(alteration),
+            _ => {}
+        }
+    }
+
+    (base + modifier_flat) * modifier_percent / 100
+}
+
+/// x% of the value, rounded toward 0
+///
+/// Negative percentage for a loss
+pub fn percentage_of(value: i32, percentage: i32) -> i32 {
+    value * percentage / 100
+}
+
 /// ----------INITIATIVE----------
 ///
 /// Minimun initiative: 0 -> Maximun initiative: 100
@@ -252,3 +401,149 @@ impl Default for Critical {
         Critical(1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shield_partially_absorbs_damage() {
+        let mut shield = 10;
+        assert_eq!(absorb_damage(&mut shield, 25), 15);
+        assert_eq!(shield, 0);
+    }
+
+    #[test]
+    fn shield_fully_absorbs_damage() {
+        let mut shield = 30;
+        assert_eq!(absorb_damage(&mut shield, 25), 0);
+        assert_eq!(shield, 5);
+
+        let mut shield = 25;
+        assert_eq!(absorb_damage(&mut shield, 25), 0);
+        assert_eq!(shield, 0);
+    }
+
+    #[test]
+    fn no_damage_leaves_the_shield() {
+        let mut shield = 10;
+        assert_eq!(absorb_damage(&mut shield, 0), 0);
+        assert_eq!(absorb_damage(&mut shield, -5), 0);
+        assert_eq!(shield, 10);
+    }
+
+    #[test]
+    fn leftover_damage_goes_through_to_hp() {
+        let mut hp = Hp::default();
+        let mut shield = Shield(10);
+
+        hp.current -= absorb_damage(&mut shield, 30);
+        assert_eq!(hp.current, 30);
+        assert_eq!(shield.0, 0);
+
+        // no more shield: the whole hit
+        hp.current -= absorb_damage(&mut shield, 40);
+        assert_eq!(hp.current, -10);
+    }
+
+    #[test]
+    fn shield_breaker_strips_then_bonus() {
+        let mut shield = 20;
+        assert_eq!(
+            break_shield(&mut shield, -15),
+            (15, SHIELD_BREAKER_BONUS_DAMAGE)
+        );
+        assert_eq!(shield, 5);
+
+        // can't strip more than the shield
+        let mut shield = 10;
+        assert_eq!(
+            break_shield(&mut shield, -30),
+            (10, SHIELD_BREAKER_BONUS_DAMAGE)
+        );
+        assert_eq!(shield, 0);
+    }
+
+    #[test]
+    fn shield_breaker_without_shield_has_no_bonus() {
+        let mut shield = 0;
+        assert_eq!(break_shield(&mut shield, -30), (0, 0));
+        assert_eq!(shield, 0);
+
+        // a positive shield_dealt never strips
+        let mut shield = 10;
+        assert_eq!(
+            break_shield(&mut shield, 5),
+            (0, SHIELD_BREAKER_BONUS_DAMAGE)
+        );
+        assert_eq!(shield, 10);
+    }
+
+    #[test]
+    fn percentage_of_rounds_toward_zero() {
+        assert_eq!(percentage_of(200, 50), 100);
+        assert_eq!(percentage_of(10, 33), 3);
+        assert_eq!(percentage_of(10, 0), 0);
+        assert_eq!(percentage_of(10, 150), 15);
+    }
+
+    #[test]
+    fn negative_percentage_of_is_a_loss() {
+        assert_eq!(percentage_of(50, -20), -10);
+        assert_eq!(percentage_of(10, -33), -3);
+        assert_eq!(percentage_of(-10, 33), -3);
+    }
+
+    #[test]
+    fn stats_percentage_modifies_the_base() {
+        let alterations = vec![Alteration {
+            action: AlterationAction::StatsPercentage,
+            attack: 50,
+            ..Default::default()
+        }];
+        assert_eq!(apply_modifiers(10, &alterations, |alt| alt.attack), 15);
+
+        // the other stats are untouched
+        assert_eq!(apply_modifiers(10, &alterations, |alt| alt.defense), 10);
+    }
+
+    #[test]
+    fn stats_flat_applies_before_stats_percentage() {
+        let alterations = vec![
+            Alteration {
+                action: AlterationAction::StatsPercentage,
+                attack: 50,
+                ..Default::default()
+            },
+            Alteration {
+                action: AlterationAction::StatsFlat,
+                attack: 10,
+                ..Default::default()
+            },
+            // a tick action isn't a modifier
+            Alteration {
+                action: AlterationAction::Dots,
+                attack: 100,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(apply_modifiers(10, &alterations, |alt| alt.attack), 30);
+
+        let malus = vec![Alteration {
+            action: AlterationAction::StatsPercentage,
+            attack: -25,
+            ..Default::default()
+        }];
+        assert_eq!(apply_modifiers(10, &malus, |alt| alt.attack), 7);
+    }
+
+    #[test]
+    fn shield_cost_is_clamped_to_zero() {
+        let mut shield = 10;
+        consume_shield(&mut shield, 4);
+        assert_eq!(shield, 6);
+
+        consume_shield(&mut shield, 15);
+        assert_eq!(shield, 0);
+    }
+}
@@ -41,6 +41,18 @@ pub enum Job {
     Fabicurion,
 }
 
+impl Job {
+    /// Mana regenerated each turn thanks to the job's discipline,
+    /// added to the entity's `Regeneration`.
+    pub fn mana_regeneration(&self) -> i32 {
+        match self {
+            Job::Healer | Job::Technomancian | Job::Logician => 10,
+            Job::Musician | Job::Faker => 5,
+            Job::MartialArt | Job::Fencer | Job::Fabicurion => 0,
+        }
+    }
+}
+
 #[derive(Reflect, Default, PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum MasteryTier {
     /// Will use it upsidedown
@@ -1,17 +1,583 @@
 //! All systems about Tactical Position and their transform
 
-// TODO: Move Unit / Swap
+use std::time::Duration;
 
 use bevy::{
     prelude::*,
+    utils::HashMap,
     window::{PrimaryWindow, WindowResized},
 };
+use bevy_tweening::{lens::TransformPositionLens, Animator, EaseFunction, Tween};
 
 use crate::{
-    combat::{InCombat, Player, Recruted, TacticalPlace, TacticalPosition},
-    constants::ui::{fighting_hall_position::*, FIGHTING_HALL_WIDTH},
+    combat::{
+        alterations::{
+            Alteration, AlterationAction, AlterationEnd, AlterationEndEvent, AlterationSource,
+            Auras, Immunities,
+        },
+        skills::{Movement, Skill, SkillRange, SkillType},
+        stats::{AttackSpe, Hp},
+        CurrentAlterations, InCombat, Player, Recruted, TacticalLine, TacticalPlace,
+        TacticalPosition, Team,
+    },
+    constants::{
+        combat::position::*,
+        ui::{fighting_hall_position::*, FIGHTING_HALL_WIDTH, TACTICAL_MOVE_DURATION},
+    },
+    ui::{combat_system::ActionsLogs, player_interaction::Dropped},
 };
 
+/// Number of lines in front of `position` holding at least one alive fighter of their team.
+///
+/// The closest line to the enemies holding someone alive counts as the front line (0),
+/// even if it is the `MiddleLine`.
+///
+/// `team_positions`: The positions of the alive fighters of the team
+pub fn depth(position: &TacticalPosition, team_positions: &[TacticalPosition]) -> usize {
+    [
+        TacticalLine::FrontLine,
+        TacticalLine::MiddleLine,
+        TacticalLine::BackLine,
+    ]
+    .iter()
+    .filter(|line| {
+        **line < position.line() && team_positions.iter().any(|ally| ally.line() == **line)
+    })
+    .count()
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                 Formations                                 */
+/* -------------------------------------------------------------------------- */
+
+/// Slots held by the alive fighters of a team, on their 3x3 grid
+///
+/// There is only one fighter per slot (see `validate_formations()`).
+#[derive(Default, Debug, Clone)]
+pub struct Formation(HashMap<TacticalPosition, Entity>);
+
+impl Formation {
+    /// The fighter holding the slot
+    pub fn unit_at(&self, position: &TacticalPosition) -> Option<Entity> {
+        self.0.get(position).copied()
+    }
+
+    /// The slot held by the fighter
+    pub fn position_of(&self, unit: Entity) -> Option<TacticalPosition> {
+        self.0
+            .iter()
+            .find(|(_, holder)| **holder == unit)
+            .map(|(position, _)| *position)
+    }
+
+    /// The fighters next to `unit` (no diagonal)
+    pub fn neighbours(&self, unit: Entity) -> Vec<Entity> {
+        let Some(unit_position) = self.position_of(unit) else {
+            return Vec::new();
+        };
+        self.0
+            .iter()
+            .filter(|(position, _)| position.is_adjacent_to(&unit_position))
+            .map(|(_, neighbour)| *neighbour)
+            .collect()
+    }
+
+    /// The closest line to the enemies holding someone
+    pub fn front_most_line(&self) -> Option<TacticalLine> {
+        self.0.keys().map(|position| position.line()).min()
+    }
+
+    /// The free slot closest to `position`, `position` itself if free
+    ///
+    /// Prefer the slots in the same line, then the closest to the front.
+    pub fn closest_free_slot(&self, position: &TacticalPosition) -> Option<TacticalPosition> {
+        TacticalPosition::all()
+            .into_iter()
+            .filter(|slot| !self.0.contains_key(slot))
+            .min_by_key(|slot| {
+                (
+                    slot.distance(position),
+                    slot.line() != position.line(),
+                    slot.line(),
+                )
+            })
+    }
+
+    /// Put the fighter in the slot, freeing their previous one
+    ///
+    /// The previous holder of the slot is no longer in the formation.
+    pub fn place(&mut self, unit: Entity, position: TacticalPosition) {
+        self.0.retain(|_, holder| *holder != unit);
+        self.0.insert(position, unit);
+    }
+}
+
+/// The `Formation` of each team in combat
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct Formations(pub HashMap<Team, Formation>);
+
+/// Give a slot to each fighter joining the combat.
+///
+/// A fighter on a slot already held by a teammate is relocated to the closest free slot,
+/// or rejected (no longer `InCombat`) if their team's grid is full.
+///
+/// The fighters are placed in the order of their `InCombat` id.
+pub fn validate_formations(
+    mut commands: Commands,
+    mut formations: ResMut<Formations>,
+
+    mut new_fighters_query: Query<
+        (Entity, &InCombat, &Name, &Team, &mut TacticalPosition),
+        Added<InCombat>,
+    >,
+) {
+    let mut new_fighters: Vec<_> = new_fighters_query.iter_mut().collect();
+    new_fighters.sort_by_key(|(_, id, ..)| id.0);
+
+    for (fighter, _, name, team, mut position) in new_fighters {
+        let formation = formations.entry(*team).or_default();
+        match formation.closest_free_slot(&position) {
+            None => {
+                error!("The team of {} is full: they can't join the combat", name);
+                commands.entity(fighter).remove::<InCombat>();
+            }
+            Some(slot) => {
+                if slot != *position {
+                    warn!(
+                        "{:?} is already held: {} is relocated to {:?}",
+                        *position, name, slot
+                    );
+                    *position = slot;
+                }
+                formation.place(fighter, slot);
+            }
+        }
+    }
+}
+
+/// Rebuild the `Formations` whenever a `TacticalPosition` or the alive set changed
+pub fn update_formations(
+    changed_fighters_query: Query<
+        Entity,
+        (
+            Or<(Changed<TacticalPosition>, Changed<Hp>, Added<InCombat>)>,
+            With<InCombat>,
+        ),
+    >,
+    fighters_query: Query<(Entity, &InCombat, &Team, &Hp, &TacticalPosition)>,
+    mut formations: ResMut<Formations>,
+) {
+    if changed_fighters_query.is_empty() {
+        return;
+    }
+
+    let mut fighters: Vec<_> = fighters_query.iter().collect();
+    fighters.sort_by_key(|(_, id, ..)| id.0);
+
+    formations.clear();
+    for (fighter, _, team, hp, position) in fighters {
+        if hp.current > 0 {
+            formations
+                .entry(*team)
+                .or_default()
+                .place(fighter, *position);
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                   Damage                                   */
+/* -------------------------------------------------------------------------- */
+
+/// Is there an alive ally directly in front of `position`
+///
+/// `team_positions`: The positions of the alive fighters of the team
+pub fn is_covered(position: &TacticalPosition, team_positions: &[TacticalPosition]) -> bool {
+    position
+        .in_front()
+        .map_or(false, |front| team_positions.contains(&front))
+}
+
+/// Damage modifiers (in percentage) w.r.t. the `TacticalPosition`s
+///
+/// Added to the damage multiplier of the skills dealing damage
+/// (see `position_damage_modifier()`).
+#[derive(Resource, Reflect, Debug, Clone)]
+pub struct PositionModifiers {
+    /// Physical damage suffered in the FrontLine
+    pub front_line_physical_suffered: i32,
+    /// Damage suffered behind an alive ally (see `is_covered()`)
+    pub covered_suffered: i32,
+    /// Damage dealt from the BackLine with a `SkillRange::Melee` or `Reach` skill
+    pub back_line_melee_inflicted: i32,
+    /// Damage dealt from the BackLine with a `SkillRange::Ranged` skill
+    pub back_line_ranged_inflicted: i32,
+}
+
+impl Default for PositionModifiers {
+    fn default() -> Self {
+        PositionModifiers {
+            front_line_physical_suffered: FRONT_LINE_PHYSICAL_SUFFERED,
+            covered_suffered: COVERED_SUFFERED,
+            back_line_melee_inflicted: BACK_LINE_MELEE_INFLICTED,
+            back_line_ranged_inflicted: BACK_LINE_RANGED_INFLICTED,
+        }
+    }
+}
+
+/// Damage modifier (in percentage) of a skill w.r.t. the caster's and the target's positions
+///
+/// 0 for the skills not dealing damage
+/// (other than `SkillType::Attack`, `AttackSpe` and `ShieldBreaker`).
+pub fn position_damage_modifier(
+    modifiers: &PositionModifiers,
+    skill: &Skill,
+    caster_position: &TacticalPosition,
+    target_position: &TacticalPosition,
+    target_covered: bool,
+) -> i32 {
+    let mut modifier = match skill.skill_type {
+        SkillType::Attack | SkillType::ShieldBreaker
+            if target_position.line() == TacticalLine::FrontLine =>
+        {
+            modifiers.front_line_physical_suffered
+        }
+        SkillType::Attack | SkillType::ShieldBreaker | SkillType::AttackSpe => 0,
+        _ => return 0,
+    };
+
+    if target_covered {
+        modifier += modifiers.covered_suffered;
+    }
+    if caster_position.line() == TacticalLine::BackLine {
+        modifier += match skill.range {
+            SkillRange::Melee | SkillRange::Reach => modifiers.back_line_melee_inflicted,
+            SkillRange::Ranged => modifiers.back_line_ranged_inflicted,
+        };
+    }
+
+    modifier
+}
+
+/// Happens in
+///   - combat::skills::execute_skill
+///     - A `SkillType::Move` is executed
+///     - A skill with a `Displacement` hits its target
+///
+/// Read in
+///   - combat::tactical_position::move_fighters
+///     - Move the fighter, if their destination is free
+#[derive(Event)]
+pub struct MoveFighterEvent {
+    pub fighter: Entity,
+    /// The ally exchanging their position with the fighter (`Movement::Swap`)
+    ///
+    /// `None` for the ally directly behind the fighter
+    pub partner: Option<Entity>,
+    pub movement: Movement,
+    /// Damage dealt to the fighter, and to the ally in their way,
+    /// if the movement is blocked
+    pub collision_damage: i32,
+}
+
+/// Event Handler of `MoveFighterEvent`
+///
+/// The movement fails if:
+/// - the destination is out of the grid
+/// - the destination is held by an alive ally (except for `Movement::Swap`)
+/// - the ally to swap with is knocked out or is an enemy
+/// - one of the fighters moving is rooted (`AlterationAction::Root`)
+///
+/// A blocked forced movement (`Displacement`) deals its collision damage.
+///
+/// The transition is animated by `update_character_position()`.
+pub fn move_fighters(
+    mut move_fighter_event: EventReader<MoveFighterEvent>,
+    mut formations: ResMut<Formations>,
+
+    mut fighters_query: Query<
+        (
+            Entity,
+            &Name,
+            &Team,
+            &mut Hp,
+            &CurrentAlterations,
+            &mut TacticalPosition,
+        ),
+        With<InCombat>,
+    >,
+    mut actions_logs: ResMut<ActionsLogs>,
+) {
+    for MoveFighterEvent {
+        fighter,
+        partner,
+        movement,
+        collision_damage,
+    } in move_fighter_event.iter()
+    {
+        let Ok((_, name, team, hp, alterations, position)) = fighters_query.get(*fighter) else {
+            continue;
+        };
+        let (name, team, position) = (name.clone(), *team, *position);
+        if hp.current <= 0 {
+            continue;
+        }
+        if alterations.contains_action(AlterationAction::Root) {
+            actions_logs
+                .0
+                .push_str(&format!("\n  - {} is rooted and can't move", name));
+            continue;
+        }
+
+        let destination = match (movement, partner) {
+            (Movement::Forward, _) => position.in_front(),
+            (Movement::Backward, _) | (Movement::Swap, None) => position.behind(),
+            (Movement::Swap, Some(partner)) => fighters_query
+                .get(*partner)
+                .ok()
+                .map(|(.., partner_position)| *partner_position),
+        };
+        // the alive ally holding the destination (the partner to swap with, if any)
+        let formation = formations.entry(team).or_default();
+        let occupant = destination
+            .and_then(|destination| formation.unit_at(&destination))
+            .filter(|occupant| {
+                occupant != fighter && partner.map_or(true, |partner| *occupant == partner)
+            });
+
+        // fighters hurt by a blocked movement
+        let mut collided: Vec<Entity> = Vec::new();
+
+        match (destination, occupant) {
+            (None, _) => {
+                actions_logs
+                    .0
+                    .push_str(&format!("\n  - {} can't move further", name));
+                collided.push(*fighter);
+            }
+            (Some(_), None) if *movement == Movement::Swap => {
+                actions_logs
+                    .0
+                    .push_str(&format!("\n  - {} has no ally to swap with", name));
+            }
+            (Some(destination), None) => {
+                let Ok((.., mut position)) = fighters_query.get_mut(*fighter) else {
+                    continue;
+                };
+                *position = destination;
+                formation.place(*fighter, destination);
+                actions_logs
+                    .0
+                    .push_str(&format!("\n  - {} moves to {:?}", name, destination));
+            }
+            (Some(_), Some(occupant)) if *movement == Movement::Swap => {
+                let Ok(
+                    [(.., mut position), (_, occupant_name, _, _, occupant_alterations, mut occupant_position)],
+                ) = fighters_query.get_many_mut([*fighter, occupant])
+                else {
+                    continue;
+                };
+                if occupant_alterations.contains_action(AlterationAction::Root) {
+                    actions_logs
+                        .0
+                        .push_str(&format!("\n  - {} is rooted and can't move", occupant_name));
+                    continue;
+                }
+                std::mem::swap(&mut *position, &mut *occupant_position);
+                formation.place(*fighter, *position);
+                formation.place(occupant, *occupant_position);
+                actions_logs.0.push_str(&format!(
+                    "\n  - {} and {} swapped their positions",
+                    name, occupant_name
+                ));
+            }
+            (Some(_), Some(occupant)) => {
+                let Ok((_, occupant_name, ..)) = fighters_query.get(occupant) else {
+                    continue;
+                };
+                actions_logs.0.push_str(&format!(
+                    "\n  - {}'s way is blocked by {}",
+                    name, occupant_name
+                ));
+                collided.extend([*fighter, occupant]);
+            }
+        }
+
+        // ---- Collision ----
+        if *collision_damage > 0 {
+            for collided_fighter in collided {
+                let Ok((_, collided_name, _, mut collided_hp, ..)) =
+                    fighters_query.get_mut(collided_fighter)
+                else {
+                    continue;
+                };
+                // the knock out is handled by `skills::detect_knock_outs()`
+                collided_hp.current -= collision_damage;
+                actions_logs.0.push_str(&format!(
+                    "\n  - {} suffers {} collision dmg",
+                    collided_name, collision_damage
+                ));
+            }
+        }
+    }
+}
+
+/// Position in the Fighting Hall (in boxes) of the slot, on the allies' or the enemies' side
+fn slot_offset(position: &TacticalPosition, ally: bool) -> (f32, f32) {
+    if ally {
+        match *position {
+            TacticalPosition::FrontLine(place) => match place {
+                TacticalPlace::Left => ALLY_FRONTLINE_LEFT,
+                TacticalPlace::Middle => ALLY_FRONTLINE_MIDDLE,
+                TacticalPlace::Right => ALLY_FRONTLINE_RIGHT,
+            },
+            TacticalPosition::MiddleLine(place) => match place {
+                TacticalPlace::Left => ALLY_MIDDLELINE_LEFT,
+                TacticalPlace::Middle => ALLY_MIDDLELINE_MIDDLE,
+                TacticalPlace::Right => ALLY_MIDDLELINE_RIGHT,
+            },
+            TacticalPosition::BackLine(place) => match place {
+                TacticalPlace::Left => ALLY_BACKLINE_LEFT,
+                TacticalPlace::Middle => ALLY_BACKLINE_MIDDLE,
+                TacticalPlace::Right => ALLY_BACKLINE_RIGHT,
+            },
+        }
+    } else {
+        match *position {
+            TacticalPosition::FrontLine(place) => match place {
+                TacticalPlace::Left => ENEMY_FRONTLINE_LEFT,
+                TacticalPlace::Middle => ENEMY_FRONTLINE_MIDDLE,
+                TacticalPlace::Right => ENEMY_FRONTLINE_RIGHT,
+            },
+            TacticalPosition::MiddleLine(place) => match place {
+                TacticalPlace::Left => ENEMY_MIDDLELINE_LEFT,
+                TacticalPlace::Middle => ENEMY_MIDDLELINE_MIDDLE,
+                TacticalPlace::Right => ENEMY_MIDDLELINE_RIGHT,
+            },
+            TacticalPosition::BackLine(place) => match place {
+                TacticalPlace::Left => ENEMY_BACKLINE_LEFT,
+                TacticalPlace::Middle => ENEMY_BACKLINE_MIDDLE,
+                TacticalPlace::Right => ENEMY_BACKLINE_RIGHT,
+            },
+        }
+    }
+}
+
+/// World coordinates (z excluded) of the center of the slot, w.r.t. the window size
+pub fn slot_translation(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    position: &TacticalPosition,
+    ally: bool,
+) -> Vec2 {
+    // 56% = width of the Fighting Hall
+    // 17 = number of box / line
+    // 2 = half of the box (to point to the center)
+    let x = (window.width() * (FIGHTING_HALL_WIDTH / 100.)) / 17.;
+    let y = (window.height() * 1.) / 17.;
+
+    let (x_offset, y_offset) = slot_offset(position, ally);
+
+    // To be in the center of the box = - (x,y / 2.)
+    let window_coordinates = Vec2::new(x * x_offset - (x / 2.), y * y_offset - (y / 2.));
+
+    let transform_coordinates = camera
+        .viewport_to_world(camera_transform, window_coordinates)
+        .map(|ray| ray.origin.truncate())
+        .unwrap();
+
+    // y axe is inverted
+    Vec2::new(transform_coordinates.x, -transform_coordinates.y)
+}
+
+/// The slot of the allies' side closest to the world coordinates
+pub fn closest_ally_slot(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    world_position: Vec2,
+) -> TacticalPosition {
+    TacticalPosition::all()
+        .into_iter()
+        .min_by(|slot, other_slot| {
+            let distance = slot_translation(window, camera, camera_transform, slot, true)
+                .distance(world_position);
+            let other_distance =
+                slot_translation(window, camera, camera_transform, other_slot, true)
+                    .distance(world_position);
+            distance.total_cmp(&other_distance)
+        })
+        .unwrap()
+}
+
+/// Put the allies dropped by the player (see `ui::player_interaction::drag_unit_by_mouse()`)
+/// on the closest slot of their side.
+///
+/// The ally holding this slot takes the previous slot of the dropped one.
+/// A drop on the same slot just brings back the sprite.
+pub fn place_dropped_units(
+    mut commands: Commands,
+    mut formations: ResMut<Formations>,
+
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+
+    dropped_units_query: Query<(Entity, &Transform, &Team, &Name), (With<Dropped>, With<InCombat>)>,
+    mut positions_query: Query<(&Name, &mut TacticalPosition), With<InCombat>>,
+
+    mut update_char_pos_event: EventWriter<UpdateCharacterPositionEvent>,
+) {
+    if dropped_units_query.is_empty() {
+        return;
+    }
+    let window = window_query.get_single().unwrap();
+    let (camera, camera_transform) = camera_q.single();
+
+    for (dropped, transform, team, name) in dropped_units_query.iter() {
+        commands.entity(dropped).remove::<Dropped>();
+
+        let Ok((_, position)) = positions_query.get(dropped) else {
+            continue;
+        };
+        let position = *position;
+        let slot = closest_ally_slot(
+            window,
+            camera,
+            camera_transform,
+            transform.translation.truncate(),
+        );
+
+        if slot == position {
+            update_char_pos_event.send(UpdateCharacterPositionEvent);
+            continue;
+        }
+
+        let formation = formations.entry(*team).or_default();
+        match formation.unit_at(&slot).filter(|holder| *holder != dropped) {
+            None => {
+                let Ok((_, mut dropped_position)) = positions_query.get_mut(dropped) else {
+                    continue;
+                };
+                *dropped_position = slot;
+                formation.place(dropped, slot);
+                info!("{} is placed in {:?}", name, slot);
+            }
+            Some(holder) => {
+                let Ok([(_, mut dropped_position), (holder_name, mut holder_position)]) =
+                    positions_query.get_many_mut([dropped, holder])
+                else {
+                    continue;
+                };
+                std::mem::swap(&mut *dropped_position, &mut *holder_position);
+                formation.place(dropped, slot);
+                formation.place(holder, position);
+                info!("{} and {} swapped their positions", name, holder_name);
+            }
+        }
+    }
+}
+
 /// Happens when:
 /// - the window (size, etc) changed
 /// - one of the TacticalPosition changed
@@ -48,103 +614,354 @@ pub fn detect_window_tactical_pos_change(
 
 /// Adapt transform depending their tactical position and the window size
 ///
+/// A fighter changing of `TacticalPosition` during the combat slides to their new place,
+/// others are directly placed.
+///
 /// # Note
 ///
 /// FIXME: The window resize by automatic action (snap windows, etc) "spoil" the adaptation
 pub fn update_character_position(
     mut update_char_pos_event: EventReader<UpdateCharacterPositionEvent>,
+    mut commands: Commands,
 
     window_query: Query<&Window, With<PrimaryWindow>>,
     // query to get camera transform
     camera_q: Query<(&Camera, &GlobalTransform)>,
 
     ally_query: Query<Or<(With<Recruted>, With<Player>)>>,
-    mut characters_query: Query<(Entity, &mut Transform, &TacticalPosition, &Name), With<InCombat>>,
+    mut characters_query: Query<
+        (Entity, &mut Transform, Ref<TacticalPosition>, &Name),
+        With<InCombat>,
+    >,
 ) {
     for _ in update_char_pos_event.iter() {
         let window = window_query.get_single().unwrap();
         // assuming there is exactly one main camera entity, so query::single() is OK
         let (camera, camera_transform) = camera_q.single();
 
-        let width = window.width();
-        let height = window.height();
-
-        // 56% = width of the Fighting Hall
-        // 17 = number of box / line
-        // 2 = half of the box (to point to the center)
-        let x = (width * (FIGHTING_HALL_WIDTH / 100.)) / 17.;
-        let y = (height * 1.) / 17.;
-
-        // info!("width: {}, x: {}", width, x);
-        // info!("height: {}, y: {}", height, y);
-
         for (character, mut transform, tactical_position, _name) in characters_query.iter_mut() {
             // if recruted or player == Ally
-            let (x_offset, y_offset) = if ally_query.contains(character) {
-                match tactical_position {
-                    TacticalPosition::FrontLine(place) => match place {
-                        TacticalPlace::Left => ALLY_FRONTLINE_LEFT,
-                        TacticalPlace::Middle => ALLY_FRONTLINE_MIDDLE,
-                        TacticalPlace::Right => ALLY_FRONTLINE_RIGHT,
-                    },
-                    TacticalPosition::MiddleLine(place) => match place {
-                        TacticalPlace::Left => ALLY_MIDDLELINE_LEFT,
-                        TacticalPlace::Middle => ALLY_MIDDLELINE_MIDDLE,
-                        TacticalPlace::Right => ALLY_MIDDLELINE_RIGHT,
-                    },
-                    TacticalPosition::BackLine(place) => match place {
-                        TacticalPlace::Left => ALLY_BACKLINE_LEFT,
-                        TacticalPlace::Middle => ALLY_BACKLINE_MIDDLE,
-                        TacticalPlace::Right => ALLY_BACKLINE_RIGHT,
+            let slot = slot_translation(
+                window,
+                camera,
+                camera_transform,
+                &tactical_position,
+                ally_query.contains(character),
+            );
+            let translation = Vec3::new(slot.x, slot.y, transform.translation.z);
+
+            if tactical_position.is_changed() && !tactical_position.is_added() {
+                // moved during the combat
+                commands.entity(character).insert(Animator::new(Tween::new(
+                    EaseFunction::QuadraticInOut,
+                    Duration::from_millis(TACTICAL_MOVE_DURATION),
+                    TransformPositionLens {
+                        start: transform.translation,
+                        end: translation,
                     },
-                }
+                )));
             } else {
-                match tactical_position {
-                    TacticalPosition::FrontLine(place) => match place {
-                        TacticalPlace::Left => ENEMY_FRONTLINE_LEFT,
-                        TacticalPlace::Middle => ENEMY_FRONTLINE_MIDDLE,
-                        TacticalPlace::Right => ENEMY_FRONTLINE_RIGHT,
-                    },
-                    TacticalPosition::MiddleLine(place) => match place {
-                        TacticalPlace::Left => ENEMY_MIDDLELINE_LEFT,
-                        TacticalPlace::Middle => ENEMY_MIDDLELINE_MIDDLE,
-                        TacticalPlace::Right => ENEMY_MIDDLELINE_RIGHT,
-                    },
-                    TacticalPosition::BackLine(place) => match place {
-                        TacticalPlace::Left => ENEMY_BACKLINE_LEFT,
-                        TacticalPlace::Middle => ENEMY_BACKLINE_MIDDLE,
-                        TacticalPlace::Right => ENEMY_BACKLINE_RIGHT,
-                    },
-                }
-            };
-
-            // info!("{}", _name);
-
-            // info!("x_offset: {}, y_offset: {}", x_offset, y_offset);
-
-            // To be in the center of the box = - (x,y / 2.)
-            let window_coordinates = Vec2::new(x * x_offset - (x / 2.), y * y_offset - (y / 2.));
-            // info!(
-            //     "x_w: {}, y_w: {}",
-            //     window_coordinates.0, window_coordinates.1
-            // );
-
-            let transform_coordinates = Some(window_coordinates)
-                .and_then(|onscreen_position| {
-                    camera.viewport_to_world(camera_transform, onscreen_position)
-                })
-                .map(|ray| ray.origin.truncate())
-                .unwrap();
-            // info!(
-            //     "x_t: {}, y_t: {}",
-            //     transform_coordinates.0, transform_coordinates.1
-            // );
-
-            transform.translation.x = transform_coordinates.x;
-            // y axe is inverted
-            transform.translation.y = -transform_coordinates.y;
+                commands.entity(character).remove::<Animator<Transform>>();
+                transform.translation = translation;
+            }
 
             // info!("---------------");
         }
     }
 }
+
+/// Recompute the auras received by each fighter
+/// whenever a `TacticalPosition` or the alive set changed.
+///
+/// An aura is only emitted by an alive source to their alive allies,
+/// and follows the `StackingPolicy` of its alteration.
+///
+/// # Note
+///
+/// The receivers' alterations are only touched if their auras differ,
+/// to not refresh all the icons at each hit.
+/// The auras are compared by their name and source:
+/// the ongoing ones keep their state (e.g. `turn_count`),
+/// and the lost ones expire (`AlterationEndEvent`).
+/// The new ones are inserted w.r.t. their `StackingPolicy`,
+/// ending the alterations they evict or replace.
+pub fn update_auras(
+    changed_fighters_query: Query<
+        Entity,
+        (
+            Or<(Changed<TacticalPosition>, Changed<Hp>, Added<InCombat>)>,
+            With<InCombat>,
+        ),
+    >,
+    fighters_query: Query<
+        (
+            Entity,
+            &Team,
+            &TacticalPosition,
+            &Hp,
+            &Auras,
+            &Immunities,
+            &AttackSpe,
+            &Name,
+        ),
+        With<InCombat>,
+    >,
+    mut alterations_query: Query<(Entity, &mut CurrentAlterations), With<InCombat>>,
+
+    mut alteration_end_event: EventWriter<AlterationEndEvent>,
+) {
+    if changed_fighters_query.is_empty() {
+        return;
+    }
+
+    for (receiver, mut alterations) in alterations_query.iter_mut() {
+        let Ok((_, receiver_team, receiver_position, receiver_hp, _, immunities, ..)) =
+            fighters_query.get(receiver)
+        else {
+            continue;
+        };
+
+        let mut received_auras = CurrentAlterations::default();
+        if receiver_hp.current > 0 {
+            for (
+                source,
+                source_team,
+                source_position,
+                source_hp,
+                auras,
+                _,
+                source_attack_spe,
+                source_name,
+            ) in fighters_query.iter()
+            {
+                if source == receiver || source_team != receiver_team || source_hp.current <= 0 {
+                    continue;
+                }
+                for aura in auras.iter() {
+                    let in_line = aura
+                        .line
+                        .map_or(true, |line| line == source_position.line());
+                    if !in_line || !aura.area.covers(source_position, receiver_position) {
+                        continue;
+                    }
+                    let Some(mut alteration) = immunities.resist(aura.alteration.clone()) else {
+                        continue;
+                    };
+                    // fully resisted
+                    if alteration.duration <= 0 && aura.alteration.duration > 0 {
+                        continue;
+                    }
+                    alteration.aura = true;
+                    // the source's stats without their modifiers
+                    alteration.source = Some(AlterationSource {
+                        entity: source,
+                        name: source_name.to_string(),
+                        attack_spe: source_attack_spe.base,
+                    });
+                    received_auras.insert(alteration);
+                }
+            }
+        }
+
+        let identity = |alteration: &Alteration| {
+            (
+                alteration.name.clone(),
+                alteration.source.as_ref().map(|source| source.entity),
+            )
+        };
+        let received_identities: Vec<_> = received_auras.iter().map(identity).collect();
+        let ongoing_identities: Vec<_> = alterations
+            .iter()
+            .filter(|alteration| alteration.aura)
+            .map(identity)
+            .collect();
+
+        let is_lost = |alteration: &Alteration| {
+            alteration.aura && !received_identities.contains(&identity(alteration))
+        };
+        let new_auras: Vec<Alteration> = received_auras
+            .0
+            .into_iter()
+            .filter(|alteration| !ongoing_identities.contains(&identity(alteration)))
+            .collect();
+        if new_auras.is_empty() && !alterations.iter().any(is_lost) {
+            continue;
+        }
+
+        let (lost_auras, kept): (Vec<Alteration>, Vec<Alteration>) =
+            std::mem::take(&mut alterations.0)
+                .into_iter()
+                .partition(is_lost);
+        alterations.0 = kept;
+        let mut replaced_alterations = Vec::new();
+        for alteration in new_auras {
+            replaced_alterations.extend(alterations.insert(alteration));
+        }
+
+        for alteration in lost_auras {
+            alteration_end_event.send(AlterationEndEvent {
+                bearer: receiver,
+                alteration,
+                end: AlterationEnd::Expired,
+            });
+        }
+        for alteration in replaced_alterations {
+            alteration_end_event.send(AlterationEndEvent {
+                bearer: receiver,
+                alteration,
+                end: AlterationEnd::Removed,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_counts_the_lines_in_front_holding_someone() {
+        let team = [
+            TacticalPosition::FrontLine(TacticalPlace::Left),
+            TacticalPosition::MiddleLine(TacticalPlace::Middle),
+            TacticalPosition::BackLine(TacticalPlace::Right),
+        ];
+        assert_eq!(depth(&team[0], &team), 0);
+        assert_eq!(depth(&team[1], &team), 1);
+        assert_eq!(depth(&team[2], &team), 2);
+    }
+
+    #[test]
+    fn depth_shifts_when_the_front_line_is_empty() {
+        let team = [
+            TacticalPosition::MiddleLine(TacticalPlace::Left),
+            TacticalPosition::BackLine(TacticalPlace::Left),
+        ];
+        // the middle line is the front line
+        assert_eq!(depth(&team[0], &team), 0);
+        assert_eq!(depth(&team[1], &team), 1);
+
+        // alone in the back line
+        let team = [TacticalPosition::BackLine(TacticalPlace::Middle)];
+        assert_eq!(depth(&team[0], &team), 0);
+    }
+
+    #[test]
+    fn depth_skips_an_empty_middle_line() {
+        let team = [
+            TacticalPosition::FrontLine(TacticalPlace::Middle),
+            TacticalPosition::BackLine(TacticalPlace::Middle),
+        ];
+        assert_eq!(depth(&team[1], &team), 1);
+    }
+
+    fn full_line(line: TacticalLine) -> Formation {
+        let mut formation = Formation::default();
+        for (i, place) in [
+            TacticalPlace::Left,
+            TacticalPlace::Middle,
+            TacticalPlace::Right,
+        ]
+        .iter()
+        .enumerate()
+        {
+            formation.place(
+                Entity::from_raw(i as u32),
+                TacticalPosition::new(line, *place),
+            );
+        }
+        formation
+    }
+
+    #[test]
+    fn a_free_slot_is_kept() {
+        let formation = full_line(TacticalLine::FrontLine);
+        let position = TacticalPosition::BackLine(TacticalPlace::Right);
+        assert_eq!(formation.closest_free_slot(&position), Some(position));
+    }
+
+    #[test]
+    fn the_closest_free_slot_is_in_the_same_line_first() {
+        let mut formation = Formation::default();
+        let position = TacticalPosition::MiddleLine(TacticalPlace::Middle);
+        formation.place(Entity::from_raw(0), position);
+        // the front and back slots are as close, but not in the same line
+        assert_eq!(
+            formation.closest_free_slot(&position),
+            Some(TacticalPosition::MiddleLine(TacticalPlace::Left))
+        );
+    }
+
+    #[test]
+    fn the_closest_free_slot_is_then_the_closest_to_the_front() {
+        let formation = full_line(TacticalLine::MiddleLine);
+        // the front and back slots are as close
+        assert_eq!(
+            formation.closest_free_slot(&TacticalPosition::MiddleLine(TacticalPlace::Middle)),
+            Some(TacticalPosition::FrontLine(TacticalPlace::Middle))
+        );
+    }
+
+    #[test]
+    fn a_full_grid_has_no_free_slot() {
+        let mut formation = Formation::default();
+        for (i, slot) in TacticalPosition::all().into_iter().enumerate() {
+            formation.place(Entity::from_raw(i as u32), slot);
+        }
+        assert_eq!(
+            formation.closest_free_slot(&TacticalPosition::FrontLine(TacticalPlace::Middle)),
+            None
+        );
+    }
+
+    #[test]
+    fn validate_formations_relocates_the_duplicates_and_rejects_the_extra_fighter() {
+        let mut world = World::new();
+        world.init_resource::<Formations>();
+        let position = TacticalPosition::FrontLine(TacticalPlace::Middle);
+        // one more fighter than the slots, all on the same one
+        let fighters: Vec<Entity> = (0..10)
+            .map(|id| {
+                world
+                    .spawn((
+                        InCombat(id),
+                        Name::new(format!("Fighter {}", id)),
+                        Team(Some(0)),
+                        position,
+                    ))
+                    .id()
+            })
+            .collect();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(validate_formations);
+        schedule.run(&mut world);
+
+        let position_of = |fighter: Entity| *world.get::<TacticalPosition>(fighter).unwrap();
+        assert_eq!(position_of(fighters[0]), position);
+        assert_eq!(
+            position_of(fighters[1]),
+            TacticalPosition::FrontLine(TacticalPlace::Left)
+        );
+        assert_eq!(
+            position_of(fighters[2]),
+            TacticalPosition::FrontLine(TacticalPlace::Right)
+        );
+        assert_eq!(
+            position_of(fighters[3]),
+            TacticalPosition::MiddleLine(TacticalPlace::Middle)
+        );
+
+        let slots: Vec<TacticalPosition> = fighters[..9]
+            .iter()
+            .map(|fighter| position_of(*fighter))
+            .collect();
+        assert!(TacticalPosition::all()
+            .iter()
+            .all(|slot| slots.contains(slot)));
+        assert!(fighters[..9]
+            .iter()
+            .all(|fighter| world.get::<InCombat>(*fighter).is_some()));
+        assert!(world.get::<InCombat>(fighters[9]).is_none());
+    }
+}
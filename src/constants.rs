@@ -53,10 +53,28 @@ pub mod character {
 pub mod combat {
 
     pub const BASE_ACTION_COUNT: usize = 1;
+    /// Mana gained each turn, before the job's bonus
+    pub const BASE_MANA_REGENERATION: i32 = 5;
+    /// Percentage of the shields lost each turn
+    pub const BASE_SHIELD_DECAY: i32 = 0;
     pub const MAX_PARTY: usize = 6;
     pub const FIRST_ALLY_ID: usize = 0;
     pub const FIRST_ENEMY_ID: usize = MAX_PARTY;
 
+    /// Damage modifiers (in percentage) w.r.t. the `TacticalPosition`s
+    ///
+    /// See `tactical_position::PositionModifiers`
+    pub mod position {
+        /// Physical damage suffered in the FrontLine
+        pub const FRONT_LINE_PHYSICAL_SUFFERED: i32 = 20;
+        /// Damage suffered behind an alive ally
+        pub const COVERED_SUFFERED: i32 = -25;
+        /// Damage dealt from the BackLine with a `SkillRange::Melee` or `Reach` skill
+        pub const BACK_LINE_MELEE_INFLICTED: i32 = -25;
+        /// Damage dealt from the BackLine with a `SkillRange::Ranged` skill
+        pub const BACK_LINE_RANGED_INFLICTED: i32 = 15;
+    }
+
     pub mod team {
         pub const TEAM_MC: i32 = 0;
         pub const TEAM_OLF: i32 = 1;
@@ -68,6 +86,10 @@ pub mod combat {
 
         pub const BAM: i32 = 150;
 
+        /// Bonus damage (in percentage) of a `SkillType::ShieldBreaker`
+        /// if the target had some shield before the hit
+        pub const SHIELD_BREAKER_BONUS_DAMAGE: i32 = 50;
+
         pub const HOLY_SPELL_01_START_INDEX: usize = 16;
         pub const HOLY_SPELL_01_END_INDEX: usize = 22;
         pub const HOLY_SPELL_02_START_INDEX: usize = 0;
@@ -94,6 +116,11 @@ pub mod ui {
     pub const FIGHTING_HALL_WIDTH: f32 = 56.;
     pub const INITIATIVE_BAR_WIDTH: f32 = 8.;
     pub const HUD_WALL_WIDTH: f32 = 100. - (FIGHTING_HALL_WIDTH + INITIATIVE_BAR_WIDTH);
+    /// Duration (in ms) of the animation of a fighter changing of `TacticalPosition`
+    pub const TACTICAL_MOVE_DURATION: u64 = 400;
+    /// Tint of the units hit by the area of effect of the action being built
+    pub const AREA_TARGETED_COLOR: bevy::render::color::Color =
+        bevy::render::color::Color::rgb(1., 0.6, 0.6);
 
     pub mod fighting_hall_position {
 
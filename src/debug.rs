@@ -11,18 +11,29 @@ use bevy_inspector_egui::quick::{
 use crate::{
     combat::{
         // Action,
-        alterations::{Alteration, AlterationAction},
+        alterations::{Alteration, AlterationAction, AlterationNature, AlterationSchool, StackingPolicy, Immunities, Immunity, ImmunityFilter, Trigger, TriggerEffect, Auras, Aura, AuraArea, AlterationSource},
         ActionCount,
         // CombatResources,
         CombatState,
         skills::{
             // Skill,
+            AlterationTarget,
+            Dispel,
+            Displacement,
+            SkillRange,
+            AreaOfEffect,
+            ManaEffect,
+            Movement,
             SkillType,
             TargetOption,
+            DuplicateTargets,
+            RetargetPolicy,
         },
-        stats::{Hp, Mana, Shield, Initiative, Attack, AttackSpe, Defense, DefenseSpe},
+        stats::{Hp, Mana, Regeneration, Shield, MagicShield, Initiative, Attack, AttackSpe, Defense, DefenseSpe, ArmorPenetration},
         stuff::{Equipements, WeaponType, MasteryTier, Job},
+        tactical_position::PositionModifiers,
         TacticalPlace,
+        TacticalLine,
     },
     ui::combat_system::{ActionHistory, LastTurnActionHistory, ActionsLogs},
 };
@@ -52,6 +63,10 @@ impl Plugin for DebugPlugin {
 
                 // .register_type::<TacticalPosition>()
                 .register_type::<TacticalPlace>()
+                .register_type::<TacticalLine>()
+                .register_type::<PositionModifiers>()
+                // tweak the damage modifiers in game
+                .add_plugins(ResourceInspectorPlugin::<PositionModifiers>::default())
                 
                 /* -------------------------------------------------------------------------- */
                 /*                       --- Skills and Alterations ---                       */
@@ -59,10 +74,31 @@ impl Plugin for DebugPlugin {
 
                 .register_type::<Alteration>()
                 .register_type::<AlterationAction>()
+                .register_type::<StackingPolicy>()
+                .register_type::<AlterationNature>()
+                .register_type::<AlterationSchool>()
+                .register_type::<Dispel>()
+                .register_type::<Immunities>()
+                .register_type::<Immunity>()
+                .register_type::<ImmunityFilter>()
+                .register_type::<Trigger>()
+                .register_type::<TriggerEffect>()
+                .register_type::<Auras>()
+                .register_type::<Aura>()
+                .register_type::<AuraArea>()
+                .register_type::<AlterationSource>()
                 .register_type::<TargetOption>()
+                .register_type::<DuplicateTargets>()
+                .register_type::<RetargetPolicy>()
+                .register_type::<AlterationTarget>()
+                .register_type::<SkillRange>()
+                .register_type::<AreaOfEffect>()
                 
                 // .register_type::<Skill>()
                 .register_type::<SkillType>()
+                .register_type::<ManaEffect>()
+                .register_type::<Movement>()
+                .register_type::<Displacement>()
                 
                 /* -------------------------------------------------------------------------- */
                 /*                               --- Weapons ---                              */
@@ -82,11 +118,14 @@ impl Plugin for DebugPlugin {
                 .register_type::<Hp>()
                 .register_type::<Mana>()
                 .register_type::<Shield>()
+                .register_type::<MagicShield>()
                 .register_type::<Initiative>()
                 .register_type::<Attack>()
                 .register_type::<AttackSpe>()
                 .register_type::<Defense>()
                 .register_type::<DefenseSpe>()
+                .register_type::<ArmorPenetration>()
+                .register_type::<Regeneration>()
 
                 /* -------------------------------------------------------------------------- */
                 /*                                 --- UI ---                                 */
@@ -45,6 +45,7 @@ pub fn add_skill_vfx(
             skill,
             caster: _caster,
             target,
+            ..
         }) = skill_execution_queue.last()
         {
             // info!("{}. {:?} to {:?}", skill.initiative, caster, target);
@@ -6,11 +6,15 @@ use bevy::prelude::*;
 use crate::{
     characters::{FabiensInfos, PersonalInfos},
     combat::{
-        alterations::AlterationAction,
+        alterations::Immunities,
         skills::Skill,
-        stats::{Attack, AttackSpe, Defense, DefenseSpe, Hp, Initiative, Mana, Shield},
+        stats::{
+            apply_modifiers, damage_reduction, Attack, AttackSpe, Defense, DefenseSpe, Hp,
+            Initiative, MagicShield, Mana, Shield,
+        },
         stuff::{Equipement, Equipements, Job, JobsMasteries, MasteryTier, SkillTiers, WeaponType},
-        ActionCount, CurrentAlterations, InCombat, Skills,
+        tactical_position::{is_covered, position_damage_modifier, PositionModifiers},
+        ActionCount, CombatResources, CurrentAlterations, InCombat, Skills, TacticalPosition, Team,
     },
     constants::ui::dialogs::*,
     ui::{
@@ -86,6 +90,7 @@ pub fn update_caster_stats_panel(
             &Hp,
             &Mana,
             &Shield,
+            &MagicShield,
             &Initiative,
             &Attack,
             &AttackSpe,
@@ -95,7 +100,14 @@ pub fn update_caster_stats_panel(
             // &Equipements,
         ),
         (
-            Or<(Added<Selected>, Changed<Hp>, Changed<Mana>)>,
+            Or<(
+                Added<Selected>,
+                Changed<Hp>,
+                Changed<Mana>,
+                Changed<Shield>,
+                Changed<MagicShield>,
+                Changed<CurrentAlterations>,
+            )>,
             With<Selected>,
             With<InCombat>,
         ),
@@ -107,6 +119,7 @@ pub fn update_caster_stats_panel(
         hp,
         mp,
         shield,
+        magic_shield,
         initiative,
         attack,
         attack_spe,
@@ -116,12 +129,13 @@ pub fn update_caster_stats_panel(
         // equipments,
     )) = selected_unit_query.get_single()
     {
-        let [mut hp_text, mut mp_text, mut shield_text, mut initiative_text, mut attack_text, mut attack_spe_text, mut defense_text, mut defense_spe_text] =
+        let [mut hp_text, mut mp_text, mut shield_text, mut magic_shield_text, mut initiative_text, mut attack_text, mut attack_spe_text, mut defense_text, mut defense_spe_text] =
             text_query
                 .get_many_mut([
                     character_sheet.health.unwrap(),
                     character_sheet.mana.unwrap(),
                     character_sheet.shield.unwrap(),
+                    character_sheet.magic_shield.unwrap(),
                     character_sheet.initiative.unwrap(),
                     character_sheet.attack.unwrap(),
                     character_sheet.attack_spe.unwrap(),
@@ -130,55 +144,86 @@ pub fn update_caster_stats_panel(
                 ])
                 .unwrap();
 
-        let mut attack_percentage: f32 = 100.;
-        let mut attack_spe_percentage: f32 = 100.;
-        let mut defense_percentage: f32 = 100.;
-        let mut defense_spe_percentage: f32 = 100.;
-
-        for alt in alterations.iter() {
-            match alt.action {
-                AlterationAction::StatsPercentage | AlterationAction::StatsFlat => {
-                    attack_percentage += alt.attack as f32;
-                    attack_spe_percentage += alt.attack_spe as f32;
-                    defense_percentage += alt.defense as f32;
-                    defense_spe_percentage += alt.defense_spe as f32;
-                }
-                _ => {}
-            }
-        }
+        let initiative = apply_modifiers(initiative.0, alterations, |alteration| {
+            alteration.initiative
+        });
+        let attack = apply_modifiers(attack.base, alterations, |alteration| alteration.attack);
+        let attack_spe = apply_modifiers(attack_spe.base, alterations, |alteration| {
+            alteration.attack_spe
+        });
+        let defense = apply_modifiers(defense.base, alterations, |alteration| alteration.defense);
+        let defense_spe = apply_modifiers(defense_spe.base, alterations, |alteration| {
+            alteration.defense_spe
+        });
 
         hp_text.sections[0].value = format!("Health: {}/{}", hp.current, hp.max);
         mp_text.sections[0].value = format!("Mana: {}/{}", mp.current, mp.max);
         shield_text.sections[0].value = format!("Shield: {}", shield.0);
-        initiative_text.sections[0].value = format!("Initiative: {}", (initiative.0 as f32));
-        attack_text.sections[0].value = format!(
-            "Attack: {}",
-            (attack.base as f32) * attack_percentage / 100.
-        );
-        attack_spe_text.sections[0].value = format!(
-            "AttackSpe: {}",
-            (attack_spe.base as f32) * attack_spe_percentage / 100.
-        );
+        magic_shield_text.sections[0].value = format!("Magic Shield: {}", magic_shield.0);
+        initiative_text.sections[0].value = format!("Initiative: {}", initiative);
+        attack_text.sections[0].value = format!("Attack: {}", attack);
+        attack_spe_text.sections[0].value = format!("AttackSpe: {}", attack_spe);
+        // reduction in percentage: armor ÷ (armor + 100)
         defense_text.sections[0].value = format!(
-            "Defense: {}",
-            (defense.base as f32) * defense_percentage / 100.
+            "Defense: {} (-{:.0}%)",
+            defense,
+            damage_reduction(defense) * 100.
         );
         defense_spe_text.sections[0].value = format!(
-            "DefenseSpe: {}",
-            (defense_spe.base as f32) * defense_spe_percentage / 100.
+            "DefenseSpe: {} (-{:.0}%)",
+            defense_spe,
+            damage_reduction(defense_spe) * 100.
         );
     }
 }
 
+/// List the immunities of the newly selected unit,
+/// to know what to avoid when browsing an enemy's sheet.
+pub fn update_immunities_displayer(
+    character_sheet: Res<CharacterSheetElements>,
+
+    selected_unit_query: Query<&Immunities, (Added<Selected>, With<InCombat>)>,
+
+    mut text_query: Query<&mut Text>,
+) {
+    if let Ok(immunities) = selected_unit_query.get_single() {
+        let mut immunities_text = text_query
+            .get_mut(character_sheet.immunities.unwrap())
+            .unwrap();
+
+        immunities_text.sections[0].value = if immunities.is_empty() {
+            String::from("Immunities: None")
+        } else {
+            let immunities_list: Vec<String> = immunities
+                .iter()
+                .map(|immunity| immunity.to_string())
+                .collect();
+            format!("Immunities: {}", immunities_list.join(", "))
+        };
+    }
+}
+
+/// Also preview the damage modifier of the current action's skill
+/// w.r.t. the positions (see `tactical_position::position_damage_modifier()`).
+///
 /// # Note
 ///
 /// DEBUG
 /// XXX: A proper clone of update_caster_stats_panel but just for target instead of caster
 pub fn update_target_stats_panel(
+    combat_resources: Res<CombatResources>,
+    position_modifiers: Res<PositionModifiers>,
+    fighters_query: Query<(Entity, &Team, &Hp, &TacticalPosition), With<InCombat>>,
     targeted_query: Query<
-        (&Name, &Hp, &Mana),
+        (Entity, &Name, &Hp, &Mana, &Shield, &MagicShield),
         (
-            Or<(Changed<Targeted>, Changed<Hp>, Changed<Mana>)>,
+            Or<(
+                Changed<Targeted>,
+                Changed<Hp>,
+                Changed<Mana>,
+                Changed<Shield>,
+                Changed<MagicShield>,
+            )>,
             With<Targeted>,
             With<InCombat>,
         ),
@@ -189,11 +234,38 @@ pub fn update_target_stats_panel(
     mut hp_query: Query<&mut Text, (With<HpMeter>, Without<MpMeter>, With<TargetMeter>)>,
     mut mp_query: Query<&mut Text, (Without<HpMeter>, With<MpMeter>, With<TargetMeter>)>,
 ) {
-    for (name, hp, mana) in targeted_query.iter() {
+    for (target, name, hp, mana, shield, magic_shield) in targeted_query.iter() {
         let mut hp_text = hp_query.single_mut();
         let mut mp_text = mp_query.single_mut();
 
-        let hp_display = format!("Target {} hp: {}", name, &hp.current.to_string());
+        let position_modifier = combat_resources
+            .history
+            .last()
+            .and_then(|action| {
+                let (_, _, _, caster_position) = fighters_query.get(action.caster).ok()?;
+                let (_, target_team, _, target_position) = fighters_query.get(target).ok()?;
+                let target_team_positions: Vec<TacticalPosition> = fighters_query
+                    .iter()
+                    .filter(|(_, team, hp, _)| *team == target_team && hp.current > 0)
+                    .map(|(_, _, _, position)| *position)
+                    .collect();
+                Some(position_damage_modifier(
+                    &position_modifiers,
+                    &action.skill,
+                    caster_position,
+                    target_position,
+                    is_covered(target_position, &target_team_positions),
+                ))
+            })
+            .unwrap_or(0);
+
+        let mut hp_display = format!(
+            "Target {} hp: {} (shield: {}, magic shield: {})",
+            name, hp.current, shield.0, magic_shield.0
+        );
+        if position_modifier != 0 {
+            hp_display.push_str(&format!(" - position: {:+}% dmg", position_modifier));
+        }
         hp_text.sections[0].value = hp_display;
 
         let mp_display = format!("Target {} mp: {}", name, &mana.current.to_string());
@@ -452,37 +524,45 @@ pub fn skill_visibility(
 }
 
 /// Updates the color of the skill,
-/// whenever the Selected entity changed or their ActionCount change
+/// whenever the Selected entity changed or their ActionCount/Alterations change
 pub fn skill_color(
     mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor),
+        (&Interaction, &Skill, &mut BackgroundColor),
         (With<Interaction>, With<Button>, With<SkillDisplayer>),
     >,
 
     changed_selected_query: Query<
-        (Entity, &Name, &ActionCount),
-        (With<Selected>, Or<(Added<Selected>, Changed<ActionCount>)>),
+        (Entity, &Name, &ActionCount, &CurrentAlterations),
+        (
+            With<Selected>,
+            Or<(
+                Added<Selected>,
+                Changed<ActionCount>,
+                Changed<CurrentAlterations>,
+            )>,
+        ),
     >,
 ) {
-    if let Ok((_, _, action_count)) = changed_selected_query.get_single() {
-        for (interaction, mut color) in &mut interaction_query {
+    if let Ok((_, _, action_count, alterations)) = changed_selected_query.get_single() {
+        for (interaction, skill, mut color) in &mut interaction_query {
+            let inactive = action_count.current == 0 || !skill.is_allowed(alterations);
             match *interaction {
                 Interaction::Pressed => {
-                    *color = if action_count.current == 0 {
+                    *color = if inactive {
                         INACTIVE_BUTTON.into()
                     } else {
                         PRESSED_BUTTON.into()
                     };
                 }
                 Interaction::Hovered => {
-                    *color = if action_count.current == 0 {
+                    *color = if inactive {
                         INACTIVE_HOVERED_BUTTON.into()
                     } else {
                         HOVERED_BUTTON.into()
                     };
                 }
                 Interaction::None => {
-                    *color = if action_count.current == 0 {
+                    *color = if inactive {
                         INACTIVE_BUTTON.into()
                     } else {
                         NORMAL_BUTTON.into()
@@ -8,8 +8,11 @@ use bevy::{
 
 use crate::{
     combat::{
+        alterations::Immunities,
         skills::Skill,
-        stats::{Attack, AttackSpe, Defense, DefenseSpe, Hp, Initiative, Mana, Shield},
+        stats::{
+            Attack, AttackSpe, Defense, DefenseSpe, Hp, Initiative, MagicShield, Mana, Shield,
+        },
         stuff::Job,
         CombatResources,
     },
@@ -170,11 +173,13 @@ pub struct CharacterSheetElements {
     pub health: Option<Entity>,
     pub mana: Option<Entity>,
     pub shield: Option<Entity>,
+    pub magic_shield: Option<Entity>,
     pub initiative: Option<Entity>,
     pub attack: Option<Entity>,
     pub attack_spe: Option<Entity>,
     pub defense: Option<Entity>,
     pub defense_spe: Option<Entity>,
+    pub immunities: Option<Entity>,
     pub base_skills: Option<Entity>,
     pub tier_2_skills: Option<Entity>,
     pub tier_1_skills: Option<Entity>,
@@ -292,6 +297,16 @@ pub fn hud_wall_setup(
         ))
         .id();
 
+    let magic_shield = commands
+        .spawn((
+            TextBundle::from_section("Magic Shield: ???", get_text_style(&asset_server, 20.))
+                .with_style(TEXT_STYLE),
+            Label,
+            Name::new("Magic Shield"),
+            MagicShield::default(),
+        ))
+        .id();
+
     let initiative = commands
         .spawn((
             TextBundle::from_section(
@@ -351,6 +366,16 @@ pub fn hud_wall_setup(
         ))
         .id();
 
+    let immunities = commands
+        .spawn((
+            TextBundle::from_section("Immunities: ???", get_text_style(&asset_server, 20.))
+                .with_style(TEXT_STYLE),
+            Label,
+            Name::new("Immunities"),
+            Immunities::default(),
+        ))
+        .id();
+
     let weapon = commands
         .spawn((
             ImageBundle {
@@ -694,11 +719,13 @@ pub fn hud_wall_setup(
                             health,
                             mana,
                             shield,
+                            magic_shield,
                             initiative,
                             attack,
                             attack_spe,
                             defense,
                             defense_spe,
+                            immunities,
                         ]);
 
                     parent
@@ -809,11 +836,13 @@ pub fn hud_wall_setup(
         health: Some(health),
         mana: Some(mana),
         shield: Some(shield),
+        magic_shield: Some(magic_shield),
         initiative: Some(initiative),
         attack: Some(attack),
         attack_spe: Some(attack_spe),
         defense: Some(defense),
         defense_spe: Some(defense_spe),
+        immunities: Some(immunities),
         base_skills: Some(base_skills),
         tier_2_skills: Some(tier_2_skills),
         tier_1_skills: Some(tier_1_skills),
@@ -1096,7 +1125,9 @@ pub fn global_ui_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                         ))
                         .with_children(|parent| {
                             parent.spawn(TextBundle::from_section(
-                                "End of Turn",
+                                // confirm the formation before the first turn
+                                // (see `player_interaction::confirm_formation_button()`)
+                                "Start Combat",
                                 get_text_style(&asset_server, 40.),
                             ));
                         });
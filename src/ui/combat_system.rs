@@ -2,12 +2,18 @@ use bevy::prelude::*;
 
 use crate::{
     combat::{
-        phases::TransitionPhaseEvent, skills::TargetOption, AlterationStatus, CombatResources,
-        CombatState, CurrentAlterations, InCombat, Team,
+        alterations::{Alteration, AlterationAction, AlterationEnd, AlterationEndEvent},
+        phases::TransitionPhaseEvent,
+        skills::{AreaOfEffect, DuplicateTargets, TargetOption},
+        stats::Hp,
+        tactical_position::depth,
+        AlterationStatus, CombatResources, CombatState, CurrentAlterations, InCombat,
+        TacticalPosition, Team,
     },
     constants::{
         character::npc::NPC_Z_BACK,
         combat::{alteration::SIZE_ALTERATION_ICON, MAX_PARTY},
+        ui::{style::get_text_style, AREA_TARGETED_COLOR},
     },
     ui::{combat_panel::CombatStateDisplayer, player_interaction::Clicked},
 };
@@ -29,6 +35,10 @@ pub struct Selected;
 #[derive(Component)]
 pub struct Targeted;
 
+/// Hit by the area of effect of the skill aimed at a `Targeted` unit
+#[derive(Component)]
+pub struct AreaTargeted;
+
 #[derive(Component)]
 pub struct HpMeter;
 
@@ -135,6 +145,10 @@ pub fn update_selected_unit(
 /// Event Handler of UpdateUnitSelectedEvent.
 /// Will accept or not a target depending of the skill currently selected.
 ///
+/// A single-target skill on enemies must target a taunter if there is any,
+/// and every enemy targeted must be in the skill's range.
+/// A skill with `DuplicateTargets::Forbidden` can't target the same unit twice.
+///
 /// # Note
 ///
 /// REFACTOR: ? - maybe merge Targeted with Selected
@@ -146,17 +160,48 @@ pub fn update_targeted_unit(
     mut event_query: EventReader<UpdateUnitTargetedEvent>,
 
     unit_selected_query: Query<(Entity, &Team), With<Selected>>,
-    combat_units_query: Query<(Entity, &Name, &Team), With<InCombat>>,
+    combat_units_query: Query<
+        (
+            Entity,
+            &Name,
+            &Team,
+            &Hp,
+            &CurrentAlterations,
+            &TacticalPosition,
+        ),
+        With<InCombat>,
+    >,
 
     mut transition_phase_event: EventWriter<TransitionPhaseEvent>,
 ) {
     for UpdateUnitTargetedEvent(clicked) in event_query.iter() {
         match combat_units_query.get(*clicked) {
             Err(e) => warn!("The entity targeted is invalid: {:?}", e),
-            Ok((character, target_name, target_team)) => {
+            Ok((character, target_name, target_team, _, target_alterations, target_position)) => {
+                let number_of_fighters = combat_resources.number_of_fighters.clone();
                 // BUG: ?
                 let last_action = combat_resources.history.last_mut().unwrap();
 
+                // ---- Range ----
+                let alive_positions = |side: &Team| -> Vec<TacticalPosition> {
+                    combat_units_query
+                        .iter()
+                        .filter(|(_, _, team, hp, _, _)| *team == side && hp.current > 0)
+                        .map(|(_, _, _, _, _, position)| *position)
+                        .collect()
+                };
+                let in_range = || -> bool {
+                    let (caster, caster_team) = unit_selected_query.single();
+                    let Ok((_, _, _, _, _, caster_position)) = combat_units_query.get(caster)
+                    else {
+                        return false;
+                    };
+                    last_action.skill.range.reaches(
+                        depth(caster_position, &alive_positions(caster_team)),
+                        depth(target_position, &alive_positions(target_team)),
+                    )
+                };
+
                 // Is it a correct target ?
                 match last_action.skill.target_option {
                     TargetOption::Ally(_) => {
@@ -166,12 +211,37 @@ pub fn update_targeted_unit(
                             continue;
                         }
                     }
-                    TargetOption::Enemy(_) => {
+                    TargetOption::Enemy(number) => {
                         let (_, caster_team) = unit_selected_query.single();
                         if target_team == caster_team {
                             info!("The target is not an enemy");
                             continue;
                         }
+                        // ---- Taunt ----
+                        if number == 1
+                            && !target_alterations.contains_action(AlterationAction::Taunt)
+                            && combat_units_query
+                                .iter()
+                                .any(|(_, _, team, hp, alterations, _)| {
+                                    team != caster_team
+                                        && hp.current > 0
+                                        && alterations.contains_action(AlterationAction::Taunt)
+                                })
+                        {
+                            info!("Another enemy is taunting");
+                            continue;
+                        }
+                        if !in_range() {
+                            info!("The target is out of range");
+                            continue;
+                        }
+                    }
+                    TargetOption::Any(_) => {
+                        let (_, caster_team) = unit_selected_query.single();
+                        if target_team != caster_team && !in_range() {
+                            info!("The target is out of range");
+                            continue;
+                        }
                     }
                     TargetOption::AllyButSelf(_) => {
                         let (caster, caster_team) = unit_selected_query.single();
@@ -182,37 +252,31 @@ pub fn update_targeted_unit(
                     }
                     _ => {}
                 }
+                // ---- Duplicates ----
+                if last_action.skill.duplicate_targets == DuplicateTargets::Forbidden
+                    && last_action
+                        .targets
+                        .as_ref()
+                        .map_or(false, |targets| targets.contains(&character))
+                {
+                    info!("{} is already targeted", target_name);
+                    continue;
+                }
 
                 commands.entity(character).insert(Targeted);
                 info!("{} targeted", target_name);
 
                 // Possibility to target multiple depending to the skill selected
                 last_action.targets = match last_action.targets.clone() {
-                    None => {
-                        // Number of target = max targetable
-                        match last_action.skill.target_option {
-                            TargetOption::Ally(1)
-                            | TargetOption::Enemy(1)
-                            | TargetOption::OneSelf => transition_phase_event
-                                .send(TransitionPhaseEvent(CombatState::default())),
-                            _ => {}
-                        }
-                        Some(vec![character])
-                    }
+                    None => Some(vec![character]),
                     Some(mut targets) => {
                         match last_action.skill.target_option {
                             TargetOption::Ally(number)
                             | TargetOption::Enemy(number)
-                            | TargetOption::AllyButSelf(number) => {
-                                // Only work if we can target muiltiple times one entity
-                                // or if the number of available target is < number asked
-                                // TODO: can target less if = the max possible
+                            | TargetOption::AllyButSelf(number)
+                            | TargetOption::Any(number) => {
                                 if targets.len() < number {
                                     targets.push(character);
-                                    if targets.len() == number {
-                                        transition_phase_event
-                                            .send(TransitionPhaseEvent(CombatState::default()));
-                                    }
                                 } else if targets.len() > number {
                                     warn!(
                                         "Error! The number of target is exceeded {}/{:?}",
@@ -230,53 +294,160 @@ pub fn update_targeted_unit(
                             TargetOption::OneSelf
                             | TargetOption::AllAlly
                             | TargetOption::AllEnemy
-                            | TargetOption::All => {}
+                            | TargetOption::All
+                            | TargetOption::AllButSelf
+                            | TargetOption::RandomEnemy(_)
+                            | TargetOption::RandomAlly(_) => {}
                         }
                         Some(targets)
                     }
                 };
+
+                // A partial selection can also be confirmed
+                // (see `player_interaction::confirm_targets()`)
+                if last_action.is_full(&number_of_fighters) {
+                    transition_phase_event.send(TransitionPhaseEvent(CombatState::default()));
+                }
             }
         }
     }
 }
 
-/// TODO: Update Alterations' icons on characters
+/// Preview the alive units hit by the area of effect (see `Skill::area`)
+/// of the action being built, around its `Targeted` units.
+///
+/// They are `AreaTargeted` and tinted with `AREA_TARGETED_COLOR`
+/// until the targets or the skill change.
+pub fn update_area_targeted_units(
+    mut commands: Commands,
+    combat_resources: Res<CombatResources>,
+
+    mut combat_units_query: Query<
+        (
+            Entity,
+            &Name,
+            &Team,
+            &Hp,
+            &TacticalPosition,
+            Option<&Targeted>,
+            Option<&AreaTargeted>,
+            &mut TextureAtlasSprite,
+        ),
+        With<InCombat>,
+    >,
+) {
+    let area = combat_resources
+        .history
+        .last()
+        .map_or(AreaOfEffect::Single, |action| action.skill.area);
+
+    let primaries: Vec<(Team, TacticalPosition)> = combat_units_query
+        .iter()
+        .filter(|(_, _, _, _, _, targeted, _, _)| targeted.is_some())
+        .map(|(_, _, team, _, position, _, _, _)| (*team, *position))
+        .collect();
+
+    for (unit, name, team, hp, position, targeted, area_targeted, mut sprite) in
+        combat_units_query.iter_mut()
+    {
+        let in_area = area != AreaOfEffect::Single
+            && targeted.is_none()
+            && hp.current > 0
+            && primaries.iter().any(|(primary_team, primary_position)| {
+                primary_team == team && area.covers(primary_position, position)
+            });
+
+        if in_area && area_targeted.is_none() {
+            commands.entity(unit).insert(AreaTargeted);
+            sprite.color = AREA_TARGETED_COLOR;
+            info!("{} is in the area", name);
+        } else if !in_area && area_targeted.is_some() {
+            commands.entity(unit).remove::<AreaTargeted>();
+            sprite.color = Color::WHITE;
+        }
+    }
+}
+
+/// Update Alterations' icons on characters
+///
+/// Stacked alterations (same name) share one icon with their stack count.
 ///
 /// Changed for duration / Added and RemovalDetection for just anim
+/// and the bearers of ended alterations (`AlterationEndEvent`)
 pub fn update_alterations_status(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
 
-    changed_alterations_query: Query<
-        (&CurrentAlterations, &Children, &Name),
-        (Changed<CurrentAlterations>, With<InCombat>),
-    >,
+    mut alteration_end_event: EventReader<AlterationEndEvent>,
+    alterations_query: Query<(Entity, Ref<CurrentAlterations>, &Children, &Name), With<InCombat>>,
 ) {
-    for (alterations, children, _name) in changed_alterations_query.iter() {
+    let bearers: Vec<Entity> = alteration_end_event
+        .iter()
+        .map(|alteration_end| alteration_end.bearer)
+        .collect();
+
+    for (character, alterations, children, _name) in alterations_query.iter() {
+        if !alterations.is_changed() && !bearers.contains(&character) {
+            continue;
+        }
         // info!("{} has some alterations change", _name);
+
+        // one icon per distinct alteration
+        let mut distinct_alterations: Vec<&Alteration> = Vec::new();
+        for alteration in alterations.iter() {
+            if !distinct_alterations
+                .iter()
+                .any(|distinct| distinct.name == alteration.name)
+            {
+                distinct_alterations.push(alteration);
+            }
+        }
+
         // "Reset" all alt_displayer
         commands.entity(children[0]).despawn_descendants();
         commands.entity(children[0]).with_children(|parent| {
-            for (i, alteration) in alterations.iter().enumerate() {
-                parent.spawn((
-                    SpriteBundle {
-                        texture: asset_server.load(alteration.clone().path_icon),
-                        sprite: Sprite {
-                            // anchor: bevy::sprite::Anchor::TopCenter,
-                            custom_size: Some(Vec2::splat(SIZE_ALTERATION_ICON)),
+            for (i, alteration) in distinct_alterations.iter().enumerate() {
+                let stack_count = alterations.count(&alteration.name);
+                parent
+                    .spawn((
+                        SpriteBundle {
+                            texture: asset_server.load(alteration.path_icon.clone()),
+                            sprite: Sprite {
+                                // anchor: bevy::sprite::Anchor::TopCenter,
+                                custom_size: Some(Vec2::splat(SIZE_ALTERATION_ICON)),
+                                ..default()
+                            },
+                            transform: Transform::from_translation(Vec3::new(
+                                SIZE_ALTERATION_ICON * (i as f32)
+                                    - SIZE_ALTERATION_ICON
+                                        * (distinct_alterations.len() as f32 / 2.),
+                                -12.5,
+                                NPC_Z_BACK,
+                            )),
                             ..default()
                         },
-                        transform: Transform::from_translation(Vec3::new(
-                            SIZE_ALTERATION_ICON * (i as f32)
-                                - SIZE_ALTERATION_ICON * (alterations.len() as f32 / 2.),
-                            -12.5,
-                            NPC_Z_BACK,
-                        )),
-                        ..default()
-                    },
-                    Name::new(alteration.clone().name),
-                    AlterationStatus,
-                ));
+                        Name::new(alteration.name.clone()),
+                        AlterationStatus,
+                    ))
+                    .with_children(|parent| {
+                        if stack_count > 1 {
+                            parent.spawn((
+                                Text2dBundle {
+                                    text: Text::from_section(
+                                        format!("{}", stack_count),
+                                        get_text_style(&asset_server, SIZE_ALTERATION_ICON / 2.),
+                                    ),
+                                    transform: Transform::from_translation(Vec3::new(
+                                        SIZE_ALTERATION_ICON / 3.,
+                                        -SIZE_ALTERATION_ICON / 3.,
+                                        0.1,
+                                    )),
+                                    ..default()
+                                },
+                                Name::new("Stack Count"),
+                            ));
+                        }
+                    });
             }
         });
     }
@@ -286,6 +457,31 @@ pub fn update_alterations_status(
 /*                                   UI Logs                                  */
 /* -------------------------------------------------------------------------- */
 
+/// Log the expired alterations
+///
+/// The removed ones are logged by the skill which removed them.
+pub fn alterations_end_logs(
+    mut alteration_end_event: EventReader<AlterationEndEvent>,
+    mut actions_logs: ResMut<ActionsLogs>,
+
+    combat_units_query: Query<&Name, With<InCombat>>,
+) {
+    for AlterationEndEvent {
+        bearer,
+        alteration,
+        end,
+    } in alteration_end_event.iter()
+    {
+        if *end == AlterationEnd::Expired {
+            if let Ok(name) = combat_units_query.get(*bearer) {
+                actions_logs
+                    .0
+                    .push_str(&format!("\n{}'s {} expired", name, alteration.name));
+            }
+        }
+    }
+}
+
 /// Display the current phase
 ///
 /// # Note
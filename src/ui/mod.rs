@@ -4,7 +4,7 @@ use crate::{
     characters::FabiensInfos,
     combat::{
         CombatState,
-        tactical_position, GameState,
+        in_initialisation_phase, tactical_position, GameState,
     },
 };
 
@@ -62,7 +62,9 @@ impl Plugin for UiPlugin {
                 (
                     player_interaction::mouse_scroll,
                     player_interaction::cancel_last_input,
-                    player_interaction::select_unit_by_mouse, // .run_if(in_state(GameState::CombatWall))
+                    player_interaction::select_unit_by_mouse // .run_if(in_state(GameState::CombatWall))
+                        // the units are dragged in the formation editor
+                        .run_if(not(in_initialisation_phase)),
                 ).in_set(UiLabel::Player)
             )
             .add_systems(Update, player_interaction::action_button.after(initiative_bar::action_visibility))
@@ -91,7 +93,21 @@ impl Plugin for UiPlugin {
             
             .add_systems(
                 Update,
-                combat_system::update_alterations_status.after(CombatState::AlterationsExecution)
+                (
+                    combat_system::update_alterations_status,
+                    combat_system::alterations_end_logs,
+                )
+                    .after(CombatState::AlterationsExecution)
+            )
+            .add_systems(
+                Update,
+                (
+                    player_interaction::drag_unit_by_mouse.in_set(UiLabel::Player),
+                    player_interaction::confirm_formation_button.in_set(UiLabel::Player),
+                    tactical_position::place_dropped_units
+                        .before(tactical_position::detect_window_tactical_pos_change),
+                )
+                    .in_set(CombatState::Initialisation)
             )
             .add_systems(
                 Update,
@@ -122,6 +138,7 @@ impl Plugin for UiPlugin {
                     character_sheet::update_headers,
                     character_sheet::update_weapon_displayer,
                     character_sheet::update_caster_stats_panel.after(UiLabel::Player),
+                    character_sheet::update_immunities_displayer.after(UiLabel::Player),
                 )
                     .in_set(CombatState::SelectionSkill)
                     // .run_if(in_state(GameState::CombatWall)) // TOTEST: Keep this schedule may crash the system (event handler etc)
@@ -131,6 +148,7 @@ impl Plugin for UiPlugin {
                 (
                     combat_system::target_selection,
                     combat_system::update_targeted_unit.after(UiLabel::Player),
+                    player_interaction::confirm_targets.after(combat_system::update_targeted_unit),
 
                     // switch to a new action ----vvv
                     player_interaction::select_skill,
@@ -163,6 +181,7 @@ impl Plugin for UiPlugin {
                     character_sheet::update_headers,
                     character_sheet::update_caster_stats_panel.after(UiLabel::Player),
                     character_sheet::update_weapon_displayer,
+                    character_sheet::update_immunities_displayer.after(UiLabel::Player),
                 )
                     .in_set(CombatState::BrowseEnemySheet)
             )
@@ -182,6 +201,8 @@ impl Plugin for UiPlugin {
                         .before(CombatState::ExecuteSkills),
                     character_sheet::update_target_stats_panel
                         .after(UiLabel::Player),
+                    combat_system::update_area_targeted_units
+                        .after(UiLabel::Player),
                     initiative_bar::action_visibility
                         .after(CombatState::SelectionSkill)
                         .after(CombatState::SelectionTarget),
@@ -225,6 +246,8 @@ impl Plugin for UiPlugin {
                     tactical_position::update_character_position
                         .after(tactical_position::detect_window_tactical_pos_change),
                 )
+                    // to animate the movements in the frame they're executed
+                    .after(CombatState::ExecuteSkills)
             )
             ;
     }
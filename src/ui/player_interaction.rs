@@ -5,12 +5,14 @@ use bevy::{
     prelude::*,
     window::PrimaryWindow,
 };
+use bevy_tweening::Animator;
 
 use crate::{
     combat::{
         phases::TransitionPhaseEvent,
         skills::{Skill, TargetOption},
-        Action, ActionCount, CombatResources, CombatState, GameState, InCombat, Recruted,
+        Action, ActionCount, CombatResources, CombatState, CurrentAlterations, GameState, InCombat,
+        Recruted,
     },
     constants::{
         combat::{FIRST_ALLY_ID, FIRST_ENEMY_ID, MAX_PARTY},
@@ -208,6 +210,76 @@ pub fn select_unit_by_mouse(
     }
 }
 
+/// Drag and drop the `Draggable` units with the mouse.
+///
+/// - Pressed on a unit: The unit is `Dragged`
+/// - Held: The `Dragged` follows the cursor
+/// - Released: The `Dragged` is `Dropped`
+///
+/// # Note
+///
+/// Only used in the formation editor (`CombatState::Initialisation`),
+/// see `combat::tactical_position::place_dropped_units()`.
+pub fn drag_unit_by_mouse(
+    mut commands: Commands,
+
+    primary_query: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    buttons: Res<Input<MouseButton>>,
+
+    draggable_unit_query: Query<
+        (Entity, &Transform, &SpriteSize),
+        (With<Draggable>, Without<Dragged>),
+    >,
+    mut dragged_unit_query: Query<(Entity, &mut Transform), With<Dragged>>,
+) {
+    if buttons.just_released(MouseButton::Left) {
+        for (dragged, _) in dragged_unit_query.iter() {
+            commands.entity(dragged).remove::<Dragged>().insert(Dropped);
+        }
+        return;
+    }
+
+    let Ok(primary) = primary_query.get_single() else {
+        return;
+    };
+    let (camera, camera_transform) = camera_q.single();
+    let Some(world_position) = primary
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    else {
+        // cursor is not inside the window
+        return;
+    };
+
+    if buttons.just_pressed(MouseButton::Left) {
+        for (unit, transform, sprite_size) in draggable_unit_query.iter() {
+            let half_width = (sprite_size.width * transform.scale.x) / 2.0;
+            let half_height = (sprite_size.height * transform.scale.y) / 2.0;
+
+            if transform.translation.x - half_width < world_position.x
+                && transform.translation.x + half_width > world_position.x
+                && transform.translation.y - half_height < world_position.y
+                && transform.translation.y + half_height > world_position.y
+            {
+                // stop the potential slide to their previous slot
+                commands
+                    .entity(unit)
+                    .insert(Dragged)
+                    .remove::<Animator<Transform>>();
+
+                // prevent when clicking on overlapping entities
+                break;
+            }
+        }
+    } else if buttons.pressed(MouseButton::Left) {
+        for (_, mut transform) in dragged_unit_query.iter_mut() {
+            transform.translation.x = world_position.x;
+            transform.translation.y = world_position.y;
+        }
+    }
+}
+
 /// Action for each Interaction of the skill button
 ///
 /// # Note
@@ -227,7 +299,7 @@ pub fn select_skill(
 
     mut text_query: Query<&mut Text>,
 
-    unit_selected_query: Query<(Entity, &Name, &ActionCount), With<Selected>>,
+    unit_selected_query: Query<(Entity, &Name, &ActionCount, &CurrentAlterations), With<Selected>>,
     mut transition_phase_event: EventWriter<TransitionPhaseEvent>,
 ) {
     // TOTEST: Why does this Query triggered in a phase transi ?
@@ -244,7 +316,8 @@ pub fn select_skill(
         // if this system can run
         // we are in SelectionSkill or SelectionTarget
         // so there is a selected unit.
-        let (caster, _caster_name, action_count) = unit_selected_query.single();
+        let (caster, _caster_name, action_count, caster_alterations) = unit_selected_query.single();
+        let inactive = action_count.current == 0 || !skill.is_allowed(caster_alterations);
 
         match *interaction {
             Interaction::Pressed => {
@@ -254,6 +327,12 @@ pub fn select_skill(
                     *color = INACTIVE_BUTTON.into();
                     continue;
                 }
+                // Mute, ForcePass or Stun
+                if !skill.is_allowed(caster_alterations) {
+                    text.sections[0].value = String::from("Controlled");
+                    *color = INACTIVE_BUTTON.into();
+                    continue;
+                }
 
                 // BUG: XXX: Weird "Bug" Event/GameState related handle
                 // Prevent the Trigger of the "double press"
@@ -303,7 +382,7 @@ pub fn select_skill(
                 // TODO: feature - Hover Skill - Preview possible Target
 
                 text.sections[0].value = skill.name.clone();
-                *color = if action_count.current == 0 {
+                *color = if inactive {
                     INACTIVE_HOVERED_BUTTON.into()
                 } else {
                     HOVERED_BUTTON.into()
@@ -312,7 +391,7 @@ pub fn select_skill(
             Interaction::None => {
                 text.sections[0].value = skill.name.clone();
 
-                *color = if action_count.current == 0 {
+                *color = if inactive {
                     INACTIVE_BUTTON.into()
                 } else {
                     NORMAL_BUTTON.into()
@@ -371,6 +450,79 @@ pub fn end_of_turn_button(
     }
 }
 
+/// Start the first turn with the formation chosen by the player
+/// (while in `CombatState::Initialisation`).
+///
+/// The `EndOfTurnButton` is used as the confirm button.
+pub fn confirm_formation_button(
+    mut interaction_query: Query<
+        (&Interaction, &Children),
+        (Changed<Interaction>, With<Button>, With<EndOfTurnButton>),
+    >,
+
+    mut text_query: Query<&mut Text>,
+    dragged_unit_query: Query<Entity, Or<(With<Dragged>, With<Dropped>)>>,
+
+    mut transition_phase_event: EventWriter<TransitionPhaseEvent>,
+) {
+    for (interaction, children) in &mut interaction_query {
+        let mut text = text_query.get_mut(children[0]).unwrap();
+        match *interaction {
+            Interaction::Pressed => {
+                // wait for the unit to be placed
+                if !dragged_unit_query.is_empty() {
+                    continue;
+                }
+
+                info!("Formation - Confirmed");
+                transition_phase_event.send(TransitionPhaseEvent(CombatState::default()));
+
+                text.sections[0].value = "End of Turn".to_string();
+            }
+            Interaction::Hovered => {
+                text.sections[0].value = "Fight !".to_string();
+            }
+            Interaction::None => {
+                text.sections[0].value = "Start Combat".to_string();
+            }
+        }
+    }
+}
+
+/// If the user press 'enter' while choosing the targets,
+/// commit the current selection even if the maximum isn't reached.
+///
+/// The selection must hold at least the skill's required targets
+/// (see `Skill::min_targets` and `Action::is_correct()`).
+pub fn confirm_targets(
+    keyboard_input: Res<Input<KeyCode>>,
+    combat_resources: Res<CombatResources>,
+
+    mut transition_phase_event: EventWriter<TransitionPhaseEvent>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    let Some(last_action) = combat_resources.history.last() else {
+        return;
+    };
+    if last_action.is_correct(combat_resources.number_of_fighters.clone()) {
+        info!("Targets confirmed");
+        transition_phase_event.send(TransitionPhaseEvent(CombatState::default()));
+    } else {
+        info!(
+            "Not enough targets to confirm {}: {}/{:?}",
+            last_action.skill.name,
+            last_action
+                .targets
+                .as_ref()
+                .map_or(0, |targets| targets.len()),
+            last_action.skill.target_option
+        );
+    }
+}
+
 /// If the user press 'esc',
 /// depending of the phase we're in,
 /// will undo the previous input (predicted, not real undo)
@@ -460,7 +612,10 @@ pub fn cancel_last_input(
                             }
                             Some(ref mut targets) => {
                                 let old_target = targets.pop().unwrap();
-                                commands.entity(old_target).remove::<Targeted>();
+                                // still targeted if the skill allows duplicates
+                                if !targets.contains(&old_target) {
+                                    commands.entity(old_target).remove::<Targeted>();
+                                }
                                 if targets.len() == 0 {
                                     last_action.targets = None;
                                 }
@@ -476,7 +631,10 @@ pub fn cancel_last_input(
                                         TargetOption::OneSelf
                                         | TargetOption::All
                                         | TargetOption::AllAlly
-                                        | TargetOption::AllEnemy => {
+                                        | TargetOption::AllEnemy
+                                        | TargetOption::AllButSelf
+                                        | TargetOption::RandomEnemy(_)
+                                        | TargetOption::RandomAlly(_) => {
                                             combat_resources.history.pop();
 
                                             transition_phase_event.send(TransitionPhaseEvent(